@@ -1,19 +1,27 @@
 use std::error::Error;
-use std::fs::read_to_string;
+use std::fs::{create_dir_all, read_to_string};
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
+use lib::error::SourceError;
 use lib::player::Player;
-use lib::score::Score;
+use lib::score::{Score, ScorePartSource, ScoreSampler, ScoreSlicedSampler};
+use lib::source::sampler::detect_root;
+use lib::source::sliced_sampler::{slice_starts, SlicedSampler};
 use lib::track::Mixdown;
+use lib::utils::{read_wav, write_wav};
 
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    // Path to src .wav file
-    src_file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    // Path to src .json score file
+    #[arg(required = true)]
+    src_file: Option<PathBuf>,
 
     // sample rate of output
     #[arg(long)]
@@ -37,9 +45,63 @@ struct Args {
     beats_per_note: f32,
 }
 
+#[derive(Subcommand)]
+enum Command {
+    // Cuts a .wav file at its transients and prints a SlicedSampler source playing the slices
+    Slice {
+        // Path to src .wav file
+        src_file: PathBuf,
+
+        // from 0.0 to 1.0, higher values find softer transients
+        #[arg(long)]
+        sensitivity: Option<f32>,
+
+        // Directory to save each slice as a .wav file
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+    },
+    // Detects the pitch of a .wav file and prints a Sampler source tuned to it
+    Root {
+        // Path to src .wav file
+        src_file: PathBuf,
+    },
+}
+
+fn slice(src_file: PathBuf, sensitivity: Option<f32>, out_dir: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let (channels, sample_rate) = read_wav(&src_file)?;
+    let detected = ScoreSlicedSampler::new(src_file.clone(), None, sensitivity, None);
+    let starts = slice_starts(&detected, &channels, sample_rate);
+    let score = ScoreSlicedSampler::new(src_file.clone(), Some(starts), sensitivity, None);
+
+    if let Some(out_dir) = out_dir {
+        create_dir_all(&out_dir)?;
+        let stem = src_file.file_stem().and_then(|stem| stem.to_str()).unwrap_or("slice");
+        for (i, note) in SlicedSampler::new(score.clone())?.slices().iter().enumerate() {
+            write_wav(&out_dir.join(format!("{}_{:03}.wav", stem, i)), note.channels(), note.sample_rate())?;
+        }
+    }
+    println!("{}", serde_json::to_string_pretty(&ScorePartSource::SlicedSampler(score))?);
+
+    Ok(())
+}
+
+fn root(src_file: PathBuf) -> Result<(), Box<dyn Error>> {
+    let (root_note, fine_tune) = detect_root(&src_file)?
+        .ok_or_else(|| SourceError::new(format!("no pitch detected in {}", src_file.display())))?;
+    let score = ScoreSampler::new(src_file, Some(root_note), Some(fine_tune), None, None, None, None);
+    println!("{}", serde_json::to_string_pretty(&ScorePartSource::Sampler(score))?);
+
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
-    let json = read_to_string(args.src_file)?;
+    let src_file = match args.command {
+        Some(Command::Slice { src_file, sensitivity, out_dir }) => return slice(src_file, sensitivity, out_dir),
+        Some(Command::Root { src_file }) => return root(src_file),
+        None => args.src_file.expect("src_file is required without a subcommand"),
+    };
+    let json = read_to_string(src_file)?;
     let score: Score = serde_json::from_str(&json)?;
     let song: Result<Mixdown, Box<dyn Error>> = score.into();
     let song = song?;
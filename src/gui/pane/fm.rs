@@ -0,0 +1,171 @@
+use eframe::egui;
+
+use lib::score::{FmAlgorithm, ScoreFmOperator, ScorePartSource};
+use lib::source::fm::Fm;
+
+use crate::pane::Pane;
+use crate::pane::track::EnvelopePane;
+
+
+#[derive(Clone, Copy)]
+struct FmOperatorPane {
+    ratio: f32,
+    level: f32,
+    feedback: f32,
+    envelope: Option<EnvelopePane>,
+}
+
+impl FmOperatorPane {
+    fn new(ratio: f32, level: f32) -> Self {
+        Self {
+            ratio,
+            level,
+            feedback: 0f32,
+            envelope: None,
+        }
+    }
+}
+
+impl Pane for FmOperatorPane {
+    fn ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.horizontal(|ui| {
+            ui.label("Ratio");
+            ui.add(egui::DragValue::new(&mut self.ratio)
+                .speed(0.01)
+                .range(0.0..=f32::INFINITY)
+            );
+
+            ui.label("Level");
+            ui.add(egui::DragValue::new(&mut self.level)
+                .speed(0.01)
+                .range(0.0..=f32::INFINITY)
+            );
+
+            ui.label("Feedback");
+            ui.add(egui::DragValue::new(&mut self.feedback)
+                .speed(0.01)
+                .range(0.0..=1.0f32)
+            );
+        });
+
+        match self.envelope.as_mut() {
+            Some(envelope) => {
+                envelope.ui(ui, ctx);
+                if ui.button("Remove Envelope").clicked() {
+                    self.envelope = None;
+                }
+            },
+            None => {
+                if ui.button("Add Envelope").clicked() {
+                    self.envelope = Some(EnvelopePane::default());
+                }
+            },
+        }
+    }
+}
+
+impl From<&ScoreFmOperator> for FmOperatorPane {
+    fn from(score_operator: &ScoreFmOperator) -> Self {
+        Self {
+            ratio: score_operator.ratio(),
+            level: score_operator.level(),
+            feedback: score_operator.feedback().unwrap_or(0f32),
+            envelope: score_operator.envelope().as_ref().map(|envelope| envelope.into()),
+        }
+    }
+}
+
+impl From<&FmOperatorPane> for ScoreFmOperator {
+    fn from(operator_pane: &FmOperatorPane) -> Self {
+        Self::new(
+            operator_pane.ratio,
+            operator_pane.level,
+            Some(operator_pane.feedback),
+            operator_pane.envelope.map(|envelope| envelope.into()),
+        )
+    }
+}
+
+fn algorithm_to_str(algorithm: &FmAlgorithm) -> &'static str {
+    match algorithm {
+        FmAlgorithm::Stack => "Stack",
+        FmAlgorithm::Parallel => "Parallel",
+        FmAlgorithm::Pairs => "Pairs",
+        FmAlgorithm::Branch => "Branch",
+        FmAlgorithm::Custom { .. } => "Custom",
+    }
+}
+
+pub struct FmPane {
+    operators: Vec<FmOperatorPane>,
+    algorithm: FmAlgorithm,
+}
+
+impl FmPane {
+    pub fn from_source(source: &ScorePartSource) -> Option<Self> {
+        match source {
+            ScorePartSource::Fm { operators, algorithm } => Some(Self {
+                operators: operators.iter().map(|operator| operator.into()).collect(),
+                algorithm: algorithm.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn source(&self) -> ScorePartSource {
+        ScorePartSource::Fm {
+            operators: self.operators.iter().map(|operator| operator.into()).collect(),
+            algorithm: self.algorithm.clone(),
+        }
+    }
+}
+
+impl Default for FmPane {
+    fn default() -> Self {
+        Self {
+            operators: vec![FmOperatorPane::new(1f32, 1f32), FmOperatorPane::new(1f32, 0.5f32)],
+            algorithm: FmAlgorithm::Stack,
+        }
+    }
+}
+
+impl Pane for FmPane {
+    fn ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("Algorithm");
+                egui::ComboBox::from_id_salt("FM Algorithm")
+                    .selected_text(algorithm_to_str(&self.algorithm))
+                    .show_ui(ui, |ui| {
+                        let choices = vec![
+                            FmAlgorithm::Stack,
+                            FmAlgorithm::Parallel,
+                            FmAlgorithm::Pairs,
+                            FmAlgorithm::Branch,
+                        ];
+                        for choice in choices {
+                            let text = algorithm_to_str(&choice);
+                            ui.selectable_value(&mut self.algorithm, choice, text);
+                        }
+                    });
+
+                if self.operators.len() < Fm::MAX_OPERATORS && ui.button("Add Operator").clicked() {
+                    self.operators.push(FmOperatorPane::new(1f32, 0.5f32));
+                }
+                if self.operators.len() > Fm::MIN_OPERATORS && ui.button("Remove Operator").clicked() {
+                    self.operators.pop();
+                }
+            });
+
+            for (i, operator) in self.operators.iter_mut().enumerate() {
+                ui.push_id(i, |ui| {
+                    egui::CollapsingHeader::new(format!("Operator {}", i + 1))
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            operator.ui(ui, ctx);
+                        });
+                });
+            }
+        });
+    }
+}
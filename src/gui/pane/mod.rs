@@ -1,5 +1,8 @@
+pub mod fm;
 pub mod mixdown;
+pub mod sliced_sampler;
 pub mod sound_source;
+pub mod subtractive;
 pub mod track;
 
 use eframe::egui;
@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+
+use eframe::egui;
+
+use lib::analysis::{detect_slices, DEFAULT_SENSITIVITY};
+use lib::score::{ScorePartSource, ScoreSlicedSampler};
+use lib::source::sliced_sampler::slice_starts;
+use lib::utils::{downmix_channels, read_wav, WavData};
+
+
+pub struct SlicedSamplerPane {
+    path: PathBuf,
+    slices: Vec<usize>,
+    sensitivity: f32,
+    first_note: i32,
+    data: Vec<f32>,
+    sample_rate: u32,
+    // Smallest and largest sample of each column of the waveform.
+    peaks: Vec<(f32, f32)>,
+}
+
+impl SlicedSamplerPane {
+    const NUM_PEAKS: usize = 1024;
+    const WAVEFORM_HEIGHT: f32 = 80f32;
+
+    pub fn from_source(source: &ScorePartSource) -> Option<Self> {
+        match source {
+            ScorePartSource::SlicedSampler(sliced_sampler) => {
+                let (channels, sample_rate): WavData = read_wav(sliced_sampler.path()).unwrap_or((vec![vec![]], 0));
+                let slices = if channels[0].is_empty() {
+                    sliced_sampler.slices().clone().unwrap_or_default()
+                } else {
+                    slice_starts(sliced_sampler, &channels, sample_rate)
+                };
+                let data = downmix_channels(&channels);
+                let chunk = data.len().div_ceil(Self::NUM_PEAKS).max(1);
+                let peaks = data.chunks(chunk)
+                    .map(|chunk| chunk.iter().fold((0f32, 0f32), |(min, max), &d| (min.min(d), max.max(d))))
+                    .collect();
+
+                Some(Self {
+                    path: sliced_sampler.path().to_path_buf(),
+                    slices,
+                    sensitivity: sliced_sampler.sensitivity().unwrap_or(DEFAULT_SENSITIVITY),
+                    first_note: sliced_sampler.first_note().unwrap_or(0),
+                    data,
+                    sample_rate,
+                    peaks,
+                })
+            },
+            _ => None,
+        }
+    }
+
+    pub fn source(&self) -> ScorePartSource {
+        ScorePartSource::SlicedSampler(ScoreSlicedSampler::new(
+            self.path.clone(),
+            Some(self.slices.clone()),
+            Some(self.sensitivity),
+            Some(self.first_note),
+        ))
+    }
+
+    fn waveform_ui(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), Self::WAVEFORM_HEIGHT),
+            egui::Sense::click(),
+        );
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2f32, egui::Color32::from_gray(32));
+        if self.data.is_empty() {
+            return;
+        }
+
+        let to_x = |frame: usize| rect.left() + rect.width() * frame as f32 / self.data.len() as f32;
+        let to_frame = |x: f32| (((x - rect.left()) / rect.width()).clamp(0f32, 1f32) * self.data.len() as f32) as usize;
+        let to_y = |value: f32| rect.center().y - value.clamp(-1f32, 1f32) * rect.height() / 2f32;
+        for (i, &(min, max)) in self.peaks.iter().enumerate() {
+            let x = rect.left() + rect.width() * (i as f32 + 0.5f32) / self.peaks.len() as f32;
+            painter.line_segment(
+                [egui::pos2(x, to_y(max)), egui::pos2(x, to_y(min))],
+                egui::Stroke::new(1f32, egui::Color32::LIGHT_BLUE),
+            );
+        }
+        for (i, &start) in self.slices.iter().enumerate() {
+            let x = to_x(start);
+            painter.line_segment(
+                [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                egui::Stroke::new(1f32, egui::Color32::YELLOW),
+            );
+            painter.text(
+                egui::pos2(x + 2f32, rect.top()),
+                egui::Align2::LEFT_TOP,
+                format!("{}", self.first_note + i as i32),
+                egui::FontId::monospace(10f32),
+                egui::Color32::YELLOW,
+            );
+        }
+
+        // Clicking adds a slice, and right clicking removes the nearest one.
+        if let Some(pos) = response.interact_pointer_pos() {
+            let frame = to_frame(pos.x);
+            if response.clicked() && !self.slices.contains(&frame) {
+                self.slices.push(frame);
+                self.slices.sort_unstable();
+            }
+            if response.secondary_clicked() && self.slices.len() > 1 {
+                let nearest = (0..self.slices.len())
+                    .min_by_key(|&i| self.slices[i].abs_diff(frame))
+                    .expect("slices are not empty");
+                self.slices.remove(nearest);
+            }
+        }
+    }
+}
+
+impl crate::pane::Pane for SlicedSamplerPane {
+    fn ui(&mut self, ui: &mut egui::Ui, _: &egui::Context) {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("Sensitivity");
+                ui.add(egui::DragValue::new(&mut self.sensitivity)
+                    .speed(0.01)
+                    .range(0.0..=1.0f32)
+                );
+                if ui.button("Detect").clicked() && !self.data.is_empty() {
+                    self.slices = detect_slices(&self.data, self.sample_rate, self.sensitivity);
+                }
+                ui.label("First Note");
+                ui.add(egui::DragValue::new(&mut self.first_note));
+                ui.label(format!("{} slices", self.slices.len()));
+            });
+            self.waveform_ui(ui);
+        });
+    }
+}
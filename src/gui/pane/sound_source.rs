@@ -1,29 +1,67 @@
 use eframe::egui;
 use std::ffi::OsStr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use egui_file::FileDialog;
 
-use lib::score::ScorePartSource;
+use lib::score::{ModalExcitation, ModalPreset, MultiSamplerZones, NoiseColor, PlaybackMode, ScoreGranular, ScoreKarpusStrong, ScorePartial, ScorePartSource, ScorePlayback, ScoreSampler, ScoreSlicedSampler, StretchEngine, WavetableFrames};
+use lib::source::granular::Granular;
+use lib::source::noise::Noise;
+use lib::source::pulse::Pulse;
+use lib::source::sampler::detect_root;
+use lib::source::wavetable::Wavetable;
 
 use crate::pane::Pane;
+use crate::pane::fm::FmPane;
+use crate::pane::sliced_sampler::SlicedSamplerPane;
+use crate::pane::subtractive::SubtractivePane;
 
 
 #[derive(Clone, Copy, PartialEq)]
 enum SoundSourceChoice {
     Sampler,
+    MultiSampler,
+    SoundFont,
+    Sfz,
     Sin,
     Triangle,
     KarpusStrong,
+    Square,
+    Saw,
+    Pulse,
+    Wavetable,
+    Fm,
+    Additive,
+    Noise,
+    DrumSynth,
+    Subtractive,
+    Modal,
+    Granular,
+    SlicedSampler,
 }
 
 impl SoundSourceChoice {
     fn to_str(&self) -> &'static str {
         match self {
             SoundSourceChoice::Sampler => "Sampler",
+            SoundSourceChoice::MultiSampler => "MultiSampler",
+            SoundSourceChoice::SoundFont => "SoundFont",
+            SoundSourceChoice::Sfz => "SFZ",
             SoundSourceChoice::Sin => "Sin",
             SoundSourceChoice::Triangle => "Triangle",
             SoundSourceChoice::KarpusStrong => "KarpusStrong",
+            SoundSourceChoice::Square => "Square",
+            SoundSourceChoice::Saw => "Saw",
+            SoundSourceChoice::Pulse => "Pulse",
+            SoundSourceChoice::Wavetable => "Wavetable",
+            SoundSourceChoice::Fm => "FM",
+            SoundSourceChoice::Additive => "Additive",
+            SoundSourceChoice::Noise => "Noise",
+            SoundSourceChoice::DrumSynth => "DrumSynth",
+            SoundSourceChoice::Subtractive => "Subtractive",
+            SoundSourceChoice::Modal => "Modal",
+            SoundSourceChoice::Granular => "Granular",
+            SoundSourceChoice::SlicedSampler => "SlicedSampler",
         }
     }
 }
@@ -32,17 +70,125 @@ impl From<ScorePartSource> for SoundSourceChoice {
     fn from(score_part_source: ScorePartSource) -> Self {
         match score_part_source {
             ScorePartSource::Sampler(_) => SoundSourceChoice::Sampler,
+            ScorePartSource::MultiSampler(_) => SoundSourceChoice::MultiSampler,
+            ScorePartSource::SoundFont { .. } => SoundSourceChoice::SoundFont,
+            ScorePartSource::Sfz(_) => SoundSourceChoice::Sfz,
             ScorePartSource::Sin => SoundSourceChoice::Sin,
             ScorePartSource::Triangle => SoundSourceChoice::Triangle,
             ScorePartSource::KarpusStrong(_) => SoundSourceChoice::KarpusStrong,
+            ScorePartSource::Square => SoundSourceChoice::Square,
+            ScorePartSource::Saw => SoundSourceChoice::Saw,
+            ScorePartSource::Pulse { .. } => SoundSourceChoice::Pulse,
+            ScorePartSource::Wavetable { .. } => SoundSourceChoice::Wavetable,
+            ScorePartSource::Fm { .. } => SoundSourceChoice::Fm,
+            ScorePartSource::Additive(_) => SoundSourceChoice::Additive,
+            ScorePartSource::Noise { .. } => SoundSourceChoice::Noise,
+            ScorePartSource::DrumSynth { .. } => SoundSourceChoice::DrumSynth,
+            ScorePartSource::Subtractive(_) => SoundSourceChoice::Subtractive,
+            ScorePartSource::Modal { .. } => SoundSourceChoice::Modal,
+            ScorePartSource::Granular(_) => SoundSourceChoice::Granular,
+            ScorePartSource::SlicedSampler(_) => SoundSourceChoice::SlicedSampler,
         }
     }
 }
 
+fn modal_preset_to_str(preset: &ModalPreset) -> &'static str {
+    match preset {
+        ModalPreset::Marimba => "Marimba",
+        ModalPreset::Vibraphone => "Vibraphone",
+        ModalPreset::Bell => "Bell",
+        ModalPreset::Plate => "Plate",
+        ModalPreset::Custom(_) => "Custom",
+    }
+}
+
+fn seed_ui(ui: &mut egui::Ui, seed: &mut Option<u64>) {
+    let mut has_seed = seed.is_some();
+    ui.checkbox(&mut has_seed, "Seed");
+    match (has_seed, seed.as_mut()) {
+        (true, Some(value)) => {
+            ui.add(egui::DragValue::new(value));
+        },
+        (true, None) => *seed = Some(0),
+        (false, _) => *seed = None,
+    }
+}
+
+fn playback_mode_to_str(mode: Option<PlaybackMode>) -> &'static str {
+    match mode {
+        None => "Default",
+        Some(PlaybackMode::OneShot) => "One Shot",
+        Some(PlaybackMode::Loop) => "Loop",
+        Some(PlaybackMode::PingPong) => "Ping Pong",
+        Some(PlaybackMode::Stretch) => "Stretch",
+    }
+}
+
+fn stretch_engine_to_str(engine: Option<StretchEngine>) -> &'static str {
+    match engine {
+        None | Some(StretchEngine::Resample) => "Resample",
+        Some(StretchEngine::Wsola) => "WSOLA",
+        Some(StretchEngine::PhaseVocoder) => "Phase Vocoder",
+    }
+}
+
+/// Edits sampler playback settings, keeping `None` when nothing is set.
+pub fn playback_ui(ui: &mut egui::Ui, id: &str, playback: &mut Option<ScorePlayback>) {
+    let current = playback.unwrap_or_default();
+    let mut mode = current.mode();
+    let mut crossfade = current.crossfade();
+    let mut reverse = current.reverse().unwrap_or(false);
+    let mut start = current.start();
+    let mut end = current.end();
+    ui.horizontal(|ui| {
+        ui.label("Playback");
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(playback_mode_to_str(mode))
+            .show_ui(ui, |ui| {
+                let choices = [
+                    None,
+                    Some(PlaybackMode::OneShot),
+                    Some(PlaybackMode::Loop),
+                    Some(PlaybackMode::PingPong),
+                    Some(PlaybackMode::Stretch),
+                ];
+                for choice in choices {
+                    ui.selectable_value(&mut mode, choice, playback_mode_to_str(choice));
+                }
+            });
+        if mode == Some(PlaybackMode::Loop) {
+            override_ui(ui, "Crossfade", &mut crossfade, 0.01f32);
+        }
+        ui.checkbox(&mut reverse, "Reverse");
+        override_ui(ui, "Start", &mut start, 0);
+        override_ui(ui, "End", &mut end, 0);
+    });
+    let edited = ScorePlayback::new(mode, crossfade, Some(reverse).filter(|&reverse| reverse), start, end);
+    *playback = Some(edited).filter(|edited| *edited != ScorePlayback::default());
+}
+
+/// Edits a value that falls back to the file's metadata or the source's setting when `None`.
+fn override_ui<T: egui::emath::Numeric>(ui: &mut egui::Ui, label: &str, value: &mut Option<T>, default: T) {
+    let mut has_value = value.is_some();
+    ui.checkbox(&mut has_value, label);
+    match (has_value, value.as_mut()) {
+        (true, Some(value)) => {
+            ui.add(egui::DragValue::new(value));
+        },
+        (true, None) => *value = Some(default),
+        (false, _) => *value = None,
+    }
+}
+
 pub struct SoundSourcePane {
     source: ScorePartSource,
     current: SoundSourceChoice,
-    dialog: Option<FileDialog>,
+    dialog: Option<(SoundSourceChoice, FileDialog)>,
+    fm: Option<FmPane>,
+    subtractive: Option<SubtractivePane>,
+    sliced_sampler: Option<SlicedSamplerPane>,
+    // Why the last root detection found nothing.
+    detect_root_message: Option<String>,
 }
 
 impl SoundSourcePane {
@@ -51,12 +197,50 @@ impl SoundSourcePane {
             source: source.to_owned(),
             current: source.to_owned().into(),
             dialog: None,
+            fm: FmPane::from_source(&source),
+            subtractive: SubtractivePane::from_source(&source),
+            sliced_sampler: SlicedSamplerPane::from_source(&source),
+            detect_root_message: None,
         }
     }
 
     pub fn source(&self) -> ScorePartSource {
         self.source.to_owned()
     }
+
+    fn open_dialog(&mut self, choice: SoundSourceChoice, extension: &'static str) {
+        let filter = Box::new({
+            let ext = Some(OsStr::new(extension));
+            move |path: &Path| -> bool { path.extension() == ext }
+        });
+        let mut dialog = FileDialog::open_file(None).show_files_filter(filter);
+        dialog.open();
+        self.dialog = Some((choice, dialog));
+    }
+
+    fn set_source_file(&mut self, choice: SoundSourceChoice, path: PathBuf) {
+        self.detect_root_message = None;
+        self.source = match choice {
+            SoundSourceChoice::Wavetable => {
+                let position = match &self.source {
+                    ScorePartSource::Wavetable { position, .. } => *position,
+                    _ => 0f32,
+                };
+                ScorePartSource::Wavetable {
+                    frames: WavetableFrames::File { path, frame_size: None },
+                    position,
+                }
+            },
+            SoundSourceChoice::MultiSampler => ScorePartSource::MultiSampler(MultiSamplerZones::File(path)),
+            SoundSourceChoice::SoundFont => ScorePartSource::SoundFont { path, bank: 0, preset: 0 },
+            SoundSourceChoice::Sfz => ScorePartSource::Sfz(path),
+            SoundSourceChoice::Granular => ScorePartSource::Granular(ScoreGranular::new(path, None, None, None, None, None, None)),
+            SoundSourceChoice::SlicedSampler => ScorePartSource::SlicedSampler(ScoreSlicedSampler::new(path, None, None, None)),
+            _ => ScorePartSource::Sampler(ScoreSampler::new(path, None, None, None, None, None, None)),
+        };
+        self.current = choice;
+        self.sliced_sampler = SlicedSamplerPane::from_source(&self.source);
+    }
 }
 
 impl Pane for SoundSourcePane {
@@ -67,8 +251,32 @@ impl Pane for SoundSourcePane {
             egui::ComboBox::from_label(match &self.source {
                 ScorePartSource::Sin => "",
                 ScorePartSource::Triangle => "",
-                ScorePartSource::Sampler(file_name) => file_name.to_str().or(Some("")).unwrap(),
-                ScorePartSource::KarpusStrong(seed) => "",
+                ScorePartSource::Sampler(sampler) => sampler.path().to_str().unwrap_or(""),
+                ScorePartSource::MultiSampler(zones) => match zones {
+                    MultiSamplerZones::File(path) => path.to_str().unwrap_or(""),
+                    MultiSamplerZones::Zones(_) => "",
+                },
+                ScorePartSource::SoundFont { path, .. } => path.to_str().unwrap_or(""),
+                ScorePartSource::Sfz(path) => path.to_str().unwrap_or(""),
+                ScorePartSource::KarpusStrong(_) => "",
+                ScorePartSource::Square => "",
+                ScorePartSource::Saw => "",
+                ScorePartSource::Pulse { .. } => "",
+                ScorePartSource::Wavetable { frames, .. } => match frames {
+                    WavetableFrames::File { path, .. } => path.to_str().unwrap_or(""),
+                    WavetableFrames::Basic => "Basic",
+                },
+                ScorePartSource::Fm { .. } => "",
+                ScorePartSource::Additive(_) => "",
+                ScorePartSource::Noise { .. } => "",
+                ScorePartSource::DrumSynth { voices, .. } => match voices {
+                    Some(_) => "",
+                    None => "Default Kit",
+                },
+                ScorePartSource::Subtractive(_) => "",
+                ScorePartSource::Modal { .. } => "",
+                ScorePartSource::Granular(granular) => granular.path().to_str().unwrap_or(""),
+                ScorePartSource::SlicedSampler(sliced_sampler) => sliced_sampler.path().to_str().unwrap_or(""),
             })
                 .selected_text(self.current.to_str())
                 .show_ui(ui, |ui| {
@@ -76,7 +284,22 @@ impl Pane for SoundSourcePane {
                         SoundSourceChoice::Sin,
                         SoundSourceChoice::Triangle,
                         SoundSourceChoice::Sampler,
+                        SoundSourceChoice::MultiSampler,
+                        SoundSourceChoice::SoundFont,
+                        SoundSourceChoice::Sfz,
                         SoundSourceChoice::KarpusStrong,
+                        SoundSourceChoice::Square,
+                        SoundSourceChoice::Saw,
+                        SoundSourceChoice::Pulse,
+                        SoundSourceChoice::Wavetable,
+                        SoundSourceChoice::Fm,
+                        SoundSourceChoice::Additive,
+                        SoundSourceChoice::Noise,
+                        SoundSourceChoice::DrumSynth,
+                        SoundSourceChoice::Subtractive,
+                        SoundSourceChoice::Modal,
+                        SoundSourceChoice::Granular,
+                        SoundSourceChoice::SlicedSampler,
                     ];
                     for choice in choices {
                         ui.selectable_value(&mut next, choice, choice.to_str());
@@ -84,14 +307,17 @@ impl Pane for SoundSourcePane {
                 });
             if next != self.current {
                 match next {
-                    SoundSourceChoice::Sampler => {
-                        let filter = Box::new({
-                            let ext = Some(OsStr::new("wav"));
-                            move |path: &Path| -> bool { path.extension() == ext }
-                        });
-                        let mut dialog = FileDialog::open_file(None).show_files_filter(filter);
-                        dialog.open();
-                        self.dialog = Some(dialog);
+                    SoundSourceChoice::Sampler | SoundSourceChoice::Granular | SoundSourceChoice::SlicedSampler => {
+                        self.open_dialog(next, "wav");
+                    },
+                    SoundSourceChoice::MultiSampler => {
+                        self.open_dialog(next, "json");
+                    },
+                    SoundSourceChoice::SoundFont => {
+                        self.open_dialog(next, "sf2");
+                    },
+                    SoundSourceChoice::Sfz => {
+                        self.open_dialog(next, "sfz");
                     },
                     SoundSourceChoice::Sin => {
                         self.current = next;
@@ -103,20 +329,290 @@ impl Pane for SoundSourcePane {
                     },
                     SoundSourceChoice::KarpusStrong => {
                         self.current = next;
-                        self.source = ScorePartSource::KarpusStrong(None);
+                        self.source = ScorePartSource::KarpusStrong(ScoreKarpusStrong::default());
+                    },
+                    SoundSourceChoice::Square => {
+                        self.current = next;
+                        self.source = ScorePartSource::Square;
+                    },
+                    SoundSourceChoice::Saw => {
+                        self.current = next;
+                        self.source = ScorePartSource::Saw;
+                    },
+                    SoundSourceChoice::Pulse => {
+                        self.current = next;
+                        self.source = ScorePartSource::Pulse { width: Pulse::DEFAULT_WIDTH };
+                    },
+                    SoundSourceChoice::Wavetable => {
+                        self.current = next;
+                        self.source = ScorePartSource::Wavetable {
+                            frames: WavetableFrames::Basic,
+                            position: 0f32,
+                        };
+                    },
+                    SoundSourceChoice::Fm => {
+                        let fm = FmPane::default();
+                        self.current = next;
+                        self.source = fm.source();
+                        self.fm = Some(fm);
+                    },
+                    SoundSourceChoice::Additive => {
+                        self.current = next;
+                        self.source = ScorePartSource::Additive(
+                            (1..=4).map(|k| ScorePartial::new(k as f32, 1f32 / k as f32, None, None)).collect()
+                        );
+                    },
+                    SoundSourceChoice::Noise => {
+                        self.current = next;
+                        self.source = ScorePartSource::Noise {
+                            color: NoiseColor::White,
+                            seed: None,
+                            resonance: None,
+                        };
+                    },
+                    SoundSourceChoice::DrumSynth => {
+                        self.current = next;
+                        self.source = ScorePartSource::DrumSynth {
+                            voices: None,
+                            seed: None,
+                        };
+                    },
+                    SoundSourceChoice::Subtractive => {
+                        let subtractive = SubtractivePane::default();
+                        self.current = next;
+                        self.source = subtractive.source();
+                        self.subtractive = Some(subtractive);
+                    },
+                    SoundSourceChoice::Modal => {
+                        self.current = next;
+                        self.source = ScorePartSource::Modal {
+                            preset: ModalPreset::Marimba,
+                            excitation: ModalExcitation::Impulse,
+                            seed: None,
+                        };
                     },
                 }
             }
+            if self.current != SoundSourceChoice::Fm {
+                self.fm = None;
+            }
+            if self.current != SoundSourceChoice::Subtractive {
+                self.subtractive = None;
+            }
+            if self.current != SoundSourceChoice::SlicedSampler {
+                self.sliced_sampler = None;
+            }
 
-            if let Some(dialog) = &mut self.dialog {
-                if dialog.show(ctx).selected() {
-                    if let Some(wav_file) = dialog.path() {
-                        self.current = next;
-                        self.source = ScorePartSource::Sampler(wav_file.to_owned().to_path_buf());
-                        self.dialog = None;
+            if let ScorePartSource::Sampler(sampler) = &mut self.source {
+                let mut root_note = sampler.root_note();
+                let mut fine_tune = sampler.fine_tune();
+                let mut loop_start = sampler.loop_start();
+                let mut loop_end = sampler.loop_end();
+                let mut playback = sampler.playback();
+                let mut engine = sampler.engine();
+                override_ui(ui, "Root Note", &mut root_note, 0);
+                override_ui(ui, "Fine Tune", &mut fine_tune, 0f32);
+                ui.horizontal(|ui| {
+                    if ui.button("Detect Root").clicked() {
+                        self.detect_root_message = match detect_root(sampler.path()) {
+                            Ok(Some((detected_note, detected_tune))) => {
+                                root_note = Some(detected_note);
+                                fine_tune = Some(detected_tune);
+                                None
+                            },
+                            Ok(None) => Some(format!("No pitch detected in {}", sampler.path().display())),
+                            Err(error) => Some(format!("{:?}", error)),
+                        };
                     }
+                    if let Some(message) = self.detect_root_message.as_ref() {
+                        ui.label(message);
+                    }
+                });
+                override_ui(ui, "Loop Start", &mut loop_start, 0);
+                override_ui(ui, "Loop End", &mut loop_end, 0);
+                playback_ui(ui, "Sampler Playback", &mut playback);
+                ui.horizontal(|ui| {
+                    ui.label("Engine");
+                    egui::ComboBox::from_id_salt("Sampler Engine")
+                        .selected_text(stretch_engine_to_str(engine))
+                        .show_ui(ui, |ui| {
+                            for choice in [None, Some(StretchEngine::Wsola), Some(StretchEngine::PhaseVocoder)] {
+                                ui.selectable_value(&mut engine, choice, stretch_engine_to_str(choice));
+                            }
+                        });
+                });
+                *sampler = ScoreSampler::new(sampler.path().to_path_buf(), root_note, fine_tune, loop_start, loop_end,
+                    playback, engine);
+            }
+
+            if let ScorePartSource::SoundFont { bank, preset, .. } = &mut self.source {
+                ui.label("Bank");
+                ui.add(egui::DragValue::new(bank));
+                ui.label("Preset");
+                ui.add(egui::DragValue::new(preset)
+                    .range(0..=127)
+                );
+            }
+
+            if let ScorePartSource::Pulse { width } = &mut self.source {
+                ui.label("Width");
+                ui.add(egui::DragValue::new(width)
+                    .speed(0.01)
+                    .range(0.01..=0.99f32)
+                );
+            }
+
+            if let ScorePartSource::Noise { color, seed, resonance } = &mut self.source {
+                egui::ComboBox::from_id_salt("Noise Color")
+                    .selected_text(match color {
+                        NoiseColor::White => "White",
+                        NoiseColor::Pink => "Pink",
+                        NoiseColor::Brown => "Brown",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(color, NoiseColor::White, "White");
+                        ui.selectable_value(color, NoiseColor::Pink, "Pink");
+                        ui.selectable_value(color, NoiseColor::Brown, "Brown");
+                    });
+
+                seed_ui(ui, seed);
+
+                let mut is_pitched = resonance.is_some();
+                ui.checkbox(&mut is_pitched, "Pitched");
+                match (is_pitched, resonance.as_mut()) {
+                    (true, Some(value)) => {
+                        ui.label("Resonance");
+                        ui.add(egui::DragValue::new(value)
+                            .speed(0.1)
+                            .range(0.1..=f32::INFINITY)
+                        );
+                    },
+                    (true, None) => *resonance = Some(Noise::DEFAULT_RESONANCE),
+                    (false, _) => *resonance = None,
                 }
             }
+
+            if let ScorePartSource::DrumSynth { seed, .. } = &mut self.source {
+                seed_ui(ui, seed);
+            }
+
+            if let ScorePartSource::Modal { preset, excitation, seed } = &mut self.source {
+                egui::ComboBox::from_id_salt("Modal Preset")
+                    .selected_text(modal_preset_to_str(preset))
+                    .show_ui(ui, |ui| {
+                        let choices = vec![
+                            ModalPreset::Marimba,
+                            ModalPreset::Vibraphone,
+                            ModalPreset::Bell,
+                            ModalPreset::Plate,
+                        ];
+                        for choice in choices {
+                            let text = modal_preset_to_str(&choice);
+                            if ui.selectable_label(modal_preset_to_str(preset) == text, text).clicked() {
+                                *preset = choice;
+                            }
+                        }
+                    });
+
+                let mut is_noise = matches!(excitation, ModalExcitation::Noise { .. });
+                ui.checkbox(&mut is_noise, "Noise Burst");
+                match (is_noise, &mut *excitation) {
+                    (true, ModalExcitation::Noise { length }) => {
+                        ui.add(egui::DragValue::new(length)
+                            .speed(0.001)
+                            .range(0.0..=1.0f32)
+                        );
+                        seed_ui(ui, seed);
+                    },
+                    (true, ModalExcitation::Impulse) => *excitation = ModalExcitation::Noise { length: 0.005f32 },
+                    (false, _) => *excitation = ModalExcitation::Impulse,
+                }
+            }
+
+            if let ScorePartSource::Granular(granular) = &mut self.source {
+                let mut grain_size = granular.grain_size().unwrap_or(Granular::DEFAULT_GRAIN_SIZE);
+                let mut density = granular.density().unwrap_or(Granular::DEFAULT_DENSITY);
+                let mut position = granular.position().unwrap_or(Granular::DEFAULT_POSITION);
+                let mut position_jitter = granular.position_jitter().unwrap_or(Granular::DEFAULT_POSITION_JITTER);
+                let mut pitch_jitter = granular.pitch_jitter().unwrap_or(Granular::DEFAULT_PITCH_JITTER);
+                let mut seed = granular.seed();
+                ui.label("Grain Size");
+                ui.add(egui::DragValue::new(&mut grain_size)
+                    .speed(0.001)
+                    .range(0.001..=f32::INFINITY)
+                );
+                ui.label("Density");
+                ui.add(egui::DragValue::new(&mut density)
+                    .speed(0.1)
+                    .range(0.1..=f32::INFINITY)
+                );
+                ui.label("Position");
+                ui.add(egui::DragValue::new(&mut position)
+                    .speed(0.01)
+                    .range(0.0..=1.0f32)
+                );
+                ui.label("Position Jitter");
+                ui.add(egui::DragValue::new(&mut position_jitter)
+                    .speed(0.01)
+                    .range(0.0..=1.0f32)
+                );
+                ui.label("Pitch Jitter");
+                ui.add(egui::DragValue::new(&mut pitch_jitter)
+                    .speed(0.01)
+                    .range(0.0..=f32::INFINITY)
+                );
+                seed_ui(ui, &mut seed);
+                *granular = ScoreGranular::new(granular.path().to_path_buf(), Some(grain_size), Some(density), Some(position),
+                    Some(position_jitter), Some(pitch_jitter), seed);
+            }
+
+            let mut load_wavetable = false;
+            if let ScorePartSource::Wavetable { frames, position } = &mut self.source {
+                if let WavetableFrames::File { frame_size, .. } = frames {
+                    let mut size = frame_size.unwrap_or(Wavetable::DEFAULT_FRAME_SIZE);
+                    ui.label("Frame Size");
+                    ui.add(egui::DragValue::new(&mut size)
+                        .range(1..=usize::MAX)
+                    );
+                    *frame_size = Some(size);
+                    if ui.button("Basic").clicked() {
+                        *frames = WavetableFrames::Basic;
+                    }
+                }
+                load_wavetable = ui.button("Load WAV").clicked();
+                ui.label("Position");
+                ui.add(egui::DragValue::new(position)
+                    .speed(0.01)
+                    .range(0.0..=1.0f32)
+                );
+            }
+            if load_wavetable {
+                self.open_dialog(SoundSourceChoice::Wavetable, "wav");
+            }
+
+            if let Some(fm) = self.fm.as_mut() {
+                fm.ui(ui, ctx);
+                self.source = fm.source();
+            }
+            if let Some(subtractive) = self.subtractive.as_mut() {
+                subtractive.ui(ui, ctx);
+                self.source = subtractive.source();
+            }
+            if let Some(sliced_sampler) = self.sliced_sampler.as_mut() {
+                sliced_sampler.ui(ui, ctx);
+                self.source = sliced_sampler.source();
+            }
+
+            let mut selected = None;
+            if let Some((choice, dialog)) = &mut self.dialog
+                && dialog.show(ctx).selected()
+                && let Some(file) = dialog.path() {
+                selected = Some((*choice, file.to_path_buf()));
+            }
+            if let Some((choice, file)) = selected {
+                self.set_source_file(choice, file);
+                self.dialog = None;
+            }
         });
     }
 }
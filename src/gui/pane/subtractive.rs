@@ -0,0 +1,369 @@
+use eframe::egui;
+
+use lib::score::{FilterMode, LfoShape, OscillatorWaveform, ScoreFilter, ScoreLfo, ScoreOscillator, ScorePartSource, ScoreSubtractive};
+use lib::source::subtractive::Subtractive;
+
+use crate::pane::Pane;
+use crate::pane::track::EnvelopePane;
+
+
+fn waveform_to_str(waveform: &OscillatorWaveform) -> &'static str {
+    match waveform {
+        OscillatorWaveform::Sin => "Sin",
+        OscillatorWaveform::Triangle => "Triangle",
+        OscillatorWaveform::Saw => "Saw",
+        OscillatorWaveform::Square => "Square",
+        OscillatorWaveform::Pulse { .. } => "Pulse",
+    }
+}
+
+fn filter_mode_to_str(mode: &FilterMode) -> &'static str {
+    match mode {
+        FilterMode::LowPass => "Low Pass",
+        FilterMode::BandPass => "Band Pass",
+        FilterMode::HighPass => "High Pass",
+    }
+}
+
+fn lfo_shape_to_str(shape: &LfoShape) -> &'static str {
+    match shape {
+        LfoShape::Sine => "Sine",
+        LfoShape::Triangle => "Triangle",
+        LfoShape::Square => "Square",
+        LfoShape::Saw => "Saw",
+        LfoShape::SampleAndHold => "Sample & Hold",
+    }
+}
+
+#[derive(Clone, Copy)]
+struct OscillatorPane {
+    waveform: OscillatorWaveform,
+    level: f32,
+    semitone: f32,
+}
+
+impl Default for OscillatorPane {
+    fn default() -> Self {
+        Self {
+            waveform: OscillatorWaveform::Saw,
+            level: 1f32,
+            semitone: 0f32,
+        }
+    }
+}
+
+impl Pane for OscillatorPane {
+    fn ui(&mut self, ui: &mut egui::Ui, _: &egui::Context) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("Waveform")
+                .selected_text(waveform_to_str(&self.waveform))
+                .show_ui(ui, |ui| {
+                    let choices = vec![
+                        OscillatorWaveform::Sin,
+                        OscillatorWaveform::Triangle,
+                        OscillatorWaveform::Saw,
+                        OscillatorWaveform::Square,
+                        OscillatorWaveform::Pulse { width: 0.25f32 },
+                    ];
+                    for choice in choices {
+                        let text = waveform_to_str(&choice);
+                        let selected = waveform_to_str(&self.waveform) == text;
+                        if ui.selectable_label(selected, text).clicked() && !selected {
+                            self.waveform = choice;
+                        }
+                    }
+                });
+            if let OscillatorWaveform::Pulse { width } = &mut self.waveform {
+                ui.label("Width");
+                ui.add(egui::DragValue::new(width)
+                    .speed(0.01)
+                    .range(0.01..=0.99f32)
+                );
+            }
+
+            ui.label("Level");
+            ui.add(egui::DragValue::new(&mut self.level)
+                .speed(0.01)
+                .range(0.0..=f32::INFINITY)
+            );
+
+            ui.label("Semitone");
+            ui.add(egui::DragValue::new(&mut self.semitone)
+                .speed(0.1)
+            );
+        });
+    }
+}
+
+impl From<&ScoreOscillator> for OscillatorPane {
+    fn from(score_oscillator: &ScoreOscillator) -> Self {
+        Self {
+            waveform: score_oscillator.waveform(),
+            level: score_oscillator.level().unwrap_or(1f32),
+            semitone: score_oscillator.semitone().unwrap_or(0f32),
+        }
+    }
+}
+
+impl From<&OscillatorPane> for ScoreOscillator {
+    fn from(oscillator_pane: &OscillatorPane) -> Self {
+        Self::new(oscillator_pane.waveform, Some(oscillator_pane.level), Some(oscillator_pane.semitone))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct LfoPane {
+    shape: LfoShape,
+    rate: f32,
+    delay: f32,
+    fade_in: f32,
+}
+
+impl Default for LfoPane {
+    fn default() -> Self {
+        Self {
+            shape: LfoShape::Sine,
+            rate: 5f32,
+            delay: 0f32,
+            fade_in: 0f32,
+        }
+    }
+}
+
+impl Pane for LfoPane {
+    fn ui(&mut self, ui: &mut egui::Ui, _: &egui::Context) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("LFO Shape")
+                .selected_text(lfo_shape_to_str(&self.shape))
+                .show_ui(ui, |ui| {
+                    let choices = vec![
+                        LfoShape::Sine,
+                        LfoShape::Triangle,
+                        LfoShape::Square,
+                        LfoShape::Saw,
+                        LfoShape::SampleAndHold,
+                    ];
+                    for choice in choices {
+                        ui.selectable_value(&mut self.shape, choice, lfo_shape_to_str(&choice));
+                    }
+                });
+
+            ui.label("Rate");
+            ui.add(egui::DragValue::new(&mut self.rate)
+                .speed(0.1)
+                .range(0.0..=f32::INFINITY)
+            );
+
+            ui.label("Delay");
+            ui.add(egui::DragValue::new(&mut self.delay)
+                .speed(0.01)
+                .range(0.0..=f32::INFINITY)
+            );
+
+            ui.label("Fade In");
+            ui.add(egui::DragValue::new(&mut self.fade_in)
+                .speed(0.01)
+                .range(0.0..=f32::INFINITY)
+            );
+        });
+    }
+}
+
+impl From<&ScoreLfo> for LfoPane {
+    fn from(score_lfo: &ScoreLfo) -> Self {
+        Self {
+            shape: score_lfo.shape(),
+            rate: score_lfo.rate(),
+            delay: score_lfo.delay().unwrap_or(0f32),
+            fade_in: score_lfo.fade_in().unwrap_or(0f32),
+        }
+    }
+}
+
+impl From<&LfoPane> for ScoreLfo {
+    fn from(lfo_pane: &LfoPane) -> Self {
+        Self::new(lfo_pane.shape, lfo_pane.rate)
+            .with_delay(Some(lfo_pane.delay).filter(|&delay| delay > 0f32))
+            .with_fade_in(Some(lfo_pane.fade_in).filter(|&fade_in| fade_in > 0f32))
+    }
+}
+
+pub struct SubtractivePane {
+    oscillators: Vec<OscillatorPane>,
+    mode: FilterMode,
+    cutoff: f32,
+    resonance: f32,
+    key_tracking: f32,
+    filter_envelope: Option<EnvelopePane>,
+    envelope_depth: f32,
+    lfo: Option<LfoPane>,
+    lfo_pitch: f32,
+    lfo_cutoff: f32,
+}
+
+impl SubtractivePane {
+    pub fn from_source(source: &ScorePartSource) -> Option<Self> {
+        match source {
+            ScorePartSource::Subtractive(subtractive) => {
+                let filter = subtractive.filter();
+                Some(Self {
+                    oscillators: subtractive.oscillators().iter().map(|oscillator| oscillator.into()).collect(),
+                    mode: filter.mode(),
+                    cutoff: filter.cutoff(),
+                    resonance: filter.resonance(),
+                    key_tracking: filter.key_tracking().unwrap_or(0f32),
+                    filter_envelope: filter.envelope().as_ref().map(|envelope| envelope.into()),
+                    envelope_depth: filter.envelope_depth().unwrap_or(0f32),
+                    lfo: subtractive.lfo().as_ref().map(|lfo| lfo.into()),
+                    lfo_pitch: subtractive.lfo_pitch().unwrap_or(0f32),
+                    lfo_cutoff: subtractive.lfo_cutoff().unwrap_or(0f32),
+                })
+            },
+            _ => None,
+        }
+    }
+
+    pub fn source(&self) -> ScorePartSource {
+        ScorePartSource::Subtractive(ScoreSubtractive::new(
+            self.oscillators.iter().map(|oscillator| oscillator.into()).collect(),
+            ScoreFilter::new(
+                self.mode,
+                self.cutoff,
+                self.resonance,
+                Some(self.key_tracking),
+                self.filter_envelope.map(|envelope| envelope.into()),
+                Some(self.envelope_depth),
+            ),
+            self.lfo.as_ref().map(|lfo| lfo.into()),
+            Some(self.lfo_pitch),
+            Some(self.lfo_cutoff),
+        ))
+    }
+}
+
+impl Default for SubtractivePane {
+    fn default() -> Self {
+        Self {
+            oscillators: vec![OscillatorPane::default()],
+            mode: FilterMode::LowPass,
+            cutoff: 2_000f32,
+            resonance: 0.3f32,
+            key_tracking: 0.5f32,
+            filter_envelope: None,
+            envelope_depth: 0f32,
+            lfo: None,
+            lfo_pitch: 0f32,
+            lfo_cutoff: 0f32,
+        }
+    }
+}
+
+impl Pane for SubtractivePane {
+    fn ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.vertical(|ui| {
+            for (i, oscillator) in self.oscillators.iter_mut().enumerate() {
+                ui.push_id(i, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Oscillator {}", i + 1));
+                        oscillator.ui(ui, ctx);
+                    });
+                });
+            }
+            ui.horizontal(|ui| {
+                if self.oscillators.len() < Subtractive::MAX_OSCILLATORS && ui.button("Add Oscillator").clicked() {
+                    self.oscillators.push(OscillatorPane::default());
+                }
+                if self.oscillators.len() > 1 && ui.button("Remove Oscillator").clicked() {
+                    self.oscillators.pop();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Filter");
+                egui::ComboBox::from_id_salt("Filter Mode")
+                    .selected_text(filter_mode_to_str(&self.mode))
+                    .show_ui(ui, |ui| {
+                        let choices = vec![
+                            FilterMode::LowPass,
+                            FilterMode::BandPass,
+                            FilterMode::HighPass,
+                        ];
+                        for choice in choices {
+                            ui.selectable_value(&mut self.mode, choice, filter_mode_to_str(&choice));
+                        }
+                    });
+
+                ui.label("Cutoff");
+                ui.add(egui::DragValue::new(&mut self.cutoff)
+                    .speed(10.0)
+                    .range(1.0..=f32::INFINITY)
+                );
+
+                ui.label("Resonance");
+                ui.add(egui::DragValue::new(&mut self.resonance)
+                    .speed(0.01)
+                    .range(0.0..=0.99f32)
+                );
+
+                ui.label("Key Tracking");
+                ui.add(egui::DragValue::new(&mut self.key_tracking)
+                    .speed(0.01)
+                    .range(0.0..=1.0f32)
+                );
+            });
+
+            ui.push_id("Filter Envelope", |ui| {
+                match self.filter_envelope.as_mut() {
+                    Some(envelope) => {
+                        envelope.ui(ui, ctx);
+                        ui.horizontal(|ui| {
+                            ui.label("Envelope Depth");
+                            ui.add(egui::DragValue::new(&mut self.envelope_depth)
+                                .speed(0.1)
+                            );
+                            if ui.button("Remove Filter Envelope").clicked() {
+                                self.filter_envelope = None;
+                            }
+                        });
+                    },
+                    None => {
+                        if ui.button("Add Filter Envelope").clicked() {
+                            self.filter_envelope = Some(EnvelopePane::default());
+                            self.envelope_depth = 2f32;
+                        }
+                    },
+                }
+            });
+
+            let mut remove_lfo = false;
+            match self.lfo.as_mut() {
+                Some(lfo) => {
+                    ui.horizontal(|ui| {
+                        ui.label("LFO");
+                        lfo.ui(ui, ctx);
+
+                        ui.label("Pitch");
+                        ui.add(egui::DragValue::new(&mut self.lfo_pitch)
+                            .speed(0.01)
+                        );
+
+                        ui.label("Cutoff");
+                        ui.add(egui::DragValue::new(&mut self.lfo_cutoff)
+                            .speed(0.01)
+                        );
+
+                        remove_lfo = ui.button("Remove LFO").clicked();
+                    });
+                },
+                None => {
+                    if ui.button("Add LFO").clicked() {
+                        self.lfo = Some(LfoPane::default());
+                    }
+                },
+            }
+            if remove_lfo {
+                self.lfo = None;
+            }
+        });
+    }
+}
@@ -3,10 +3,13 @@ use std::error::Error;
 use eframe::egui;
 use eframe::egui::Pos2;
 
-use lib::score::{ScoreEnvelope, ScoreNote, ScorePart, ScorePartSource};
+use lib::score::{Downmix, EnvelopeCurve, LfoTarget, ScoreBend, ScoreEnvelope, ScoreGlide, ScoreLfo, ScoreLfoRoute, ScoreModulation,
+    ScoreNote, ScorePart, ScorePartSource, ScorePlayback};
+use lib::source::DEFAULT_VELOCITY;
 
 use crate::pane::Pane;
-use crate::pane::sound_source::SoundSourcePane;
+use crate::pane::sound_source::{playback_ui, SoundSourcePane};
+use crate::pane::subtractive::LfoPane;
 
 
 const NUM_SCALE: usize = 12;
@@ -27,12 +30,46 @@ impl From<Scale> for [bool; NUM_SCALE] {
     }
 }
 
+fn envelope_curve_to_str(curve: Option<EnvelopeCurve>) -> &'static str {
+    match curve {
+        None | Some(EnvelopeCurve::Linear) => "Linear",
+        Some(EnvelopeCurve::Exponential) => "Exponential",
+        Some(EnvelopeCurve::Logarithmic) => "Logarithmic",
+        Some(EnvelopeCurve::Power(_)) => "Power",
+    }
+}
+
+fn envelope_curve_ui(ui: &mut egui::Ui, id: &str, curve: &mut Option<EnvelopeCurve>) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(envelope_curve_to_str(*curve))
+        .show_ui(ui, |ui| {
+            let choices = [None, Some(EnvelopeCurve::Exponential), Some(EnvelopeCurve::Logarithmic), Some(EnvelopeCurve::Power(2f32))];
+            for choice in choices {
+                let text = envelope_curve_to_str(choice);
+                if ui.selectable_label(envelope_curve_to_str(*curve) == text, text).clicked() {
+                    *curve = choice;
+                }
+            }
+        });
+    if let Some(EnvelopeCurve::Power(power)) = curve {
+        ui.add(egui::DragValue::new(power)
+            .speed(0.1)
+            .range(0.0..=f32::INFINITY)
+        );
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct EnvelopePane {
     attack: f32,
     decay: f32,
     sustain: f32,
     release: f32,
+    delay: f32,
+    hold: f32,
+    attack_curve: Option<EnvelopeCurve>,
+    decay_curve: Option<EnvelopeCurve>,
+    release_curve: Option<EnvelopeCurve>,
 }
 
 impl EnvelopePane {
@@ -42,6 +79,11 @@ impl EnvelopePane {
             decay,
             sustain,
             release,
+            delay: 0f32,
+            hold: 0f32,
+            attack_curve: None,
+            decay_curve: None,
+            release_curve: None,
         }
     }
 }
@@ -82,6 +124,27 @@ impl Pane for EnvelopePane {
                         .range(0.0..=1.0f32)
                     );
                 });
+                ui.horizontal(|ui| {
+                    ui.label("Delay");
+                    ui.add(egui::DragValue::new(&mut self.delay)
+                        .speed(0.1)
+                        .range(0.0..=f32::INFINITY)
+                    );
+
+                    ui.label("Hold");
+                    ui.add(egui::DragValue::new(&mut self.hold)
+                        .speed(0.1)
+                        .range(0.0..=f32::INFINITY)
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Attack Curve");
+                    envelope_curve_ui(ui, "Attack Curve", &mut self.attack_curve);
+                    ui.label("Decay Curve");
+                    envelope_curve_ui(ui, "Decay Curve", &mut self.decay_curve);
+                    ui.label("Release Curve");
+                    envelope_curve_ui(ui, "Release Curve", &mut self.release_curve);
+                });
             });
     }
 }
@@ -94,39 +157,208 @@ impl From<EnvelopePane> for ScoreEnvelope {
             envelope_pane.sustain,
             envelope_pane.release,
         )
+            .with_delay(Some(envelope_pane.delay).filter(|&delay| delay > 0f32))
+            .with_hold(Some(envelope_pane.hold).filter(|&hold| hold > 0f32))
+            .with_curves(envelope_pane.attack_curve, envelope_pane.decay_curve, envelope_pane.release_curve)
     }
 }
 
 impl From<&ScoreEnvelope> for EnvelopePane {
     fn from(score_envelope: &ScoreEnvelope) -> EnvelopePane {
+        Self {
+            delay: score_envelope.delay().unwrap_or(0f32),
+            hold: score_envelope.hold().unwrap_or(0f32),
+            attack_curve: score_envelope.attack_curve(),
+            decay_curve: score_envelope.decay_curve(),
+            release_curve: score_envelope.release_curve(),
+            ..Self::new(
+                score_envelope.attack(),
+                score_envelope.decay(),
+                score_envelope.sustain(),
+                score_envelope.release(),
+            )
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct BendPane {
+    from: f32,
+    to: f32,
+    // 0 bends over the whole note.
+    time: f32,
+    curve: Option<EnvelopeCurve>,
+}
+
+impl Pane for BendPane {
+    fn ui(&mut self, ui: &mut egui::Ui, _: &egui::Context) {
+        ui.horizontal(|ui| {
+            ui.label("Bend From");
+            ui.add(egui::DragValue::new(&mut self.from)
+                .speed(0.1)
+            );
+            ui.label("To");
+            ui.add(egui::DragValue::new(&mut self.to)
+                .speed(0.1)
+            );
+            ui.label("Time");
+            ui.add(egui::DragValue::new(&mut self.time)
+                .speed(0.01)
+                .range(0.0..=f32::INFINITY)
+            );
+            envelope_curve_ui(ui, "Bend Curve", &mut self.curve);
+        });
+    }
+}
+
+impl From<BendPane> for ScoreBend {
+    fn from(bend_pane: BendPane) -> Self {
         Self::new(
-            score_envelope.attack(),
-            score_envelope.decay(),
-            score_envelope.sustain(),
-            score_envelope.release(),
+            Some(bend_pane.from).filter(|&from| from != 0f32),
+            Some(bend_pane.to).filter(|&to| to != 0f32),
+            Some(bend_pane.time).filter(|&time| time > 0f32),
+            bend_pane.curve,
         )
     }
 }
 
-#[derive(Clone, Copy)]
+impl From<ScoreBend> for BendPane {
+    fn from(score_bend: ScoreBend) -> Self {
+        Self {
+            from: score_bend.from().unwrap_or(0f32),
+            to: score_bend.to().unwrap_or(0f32),
+            time: score_bend.time().unwrap_or(0f32),
+            curve: score_bend.curve(),
+        }
+    }
+}
+
+fn lfo_target_to_str(target: LfoTarget) -> &'static str {
+    match target {
+        LfoTarget::Pitch => "Pitch",
+        LfoTarget::Amplitude => "Amplitude",
+        LfoTarget::Pan => "Pan",
+        LfoTarget::Cutoff => "Cutoff",
+        LfoTarget::Position => "Position",
+        LfoTarget::Width => "Width",
+    }
+}
+
+struct LfoRoutePane {
+    lfo: LfoPane,
+    sync: Option<f32>,
+    target: LfoTarget,
+    depth: f32,
+}
+
+impl Default for LfoRoutePane {
+    fn default() -> Self {
+        Self {
+            lfo: LfoPane::default(),
+            sync: None,
+            target: LfoTarget::Pitch,
+            depth: 0.5f32,
+        }
+    }
+}
+
+impl Pane for LfoRoutePane {
+    fn ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("LFO Target")
+                .selected_text(lfo_target_to_str(self.target))
+                .show_ui(ui, |ui| {
+                    for choice in [
+                        LfoTarget::Pitch,
+                        LfoTarget::Amplitude,
+                        LfoTarget::Pan,
+                        LfoTarget::Cutoff,
+                        LfoTarget::Position,
+                        LfoTarget::Width,
+                    ] {
+                        ui.selectable_value(&mut self.target, choice, lfo_target_to_str(choice));
+                    }
+                });
+
+            ui.label("Depth");
+            ui.add(egui::DragValue::new(&mut self.depth)
+                .speed(0.01)
+            );
+
+            let mut is_synced = self.sync.is_some();
+            if ui.checkbox(&mut is_synced, "Sync").changed() {
+                self.sync = if is_synced { Some(1f32) } else { None };
+            }
+            if let Some(sync) = self.sync.as_mut() {
+                ui.label("Beats");
+                ui.add(egui::DragValue::new(sync)
+                    .speed(0.25)
+                    .range(0.0..=f32::INFINITY)
+                );
+            }
+        });
+        self.lfo.ui(ui, ctx);
+    }
+}
+
+impl From<&ScoreLfoRoute> for LfoRoutePane {
+    fn from(score_lfo_route: &ScoreLfoRoute) -> Self {
+        Self {
+            lfo: score_lfo_route.lfo().into(),
+            sync: score_lfo_route.sync(),
+            target: score_lfo_route.target(),
+            depth: score_lfo_route.depth(),
+        }
+    }
+}
+
+impl From<&LfoRoutePane> for ScoreLfoRoute {
+    fn from(lfo_route_pane: &LfoRoutePane) -> Self {
+        Self::new(
+            ScoreLfo::from(&lfo_route_pane.lfo),
+            lfo_route_pane.target,
+            lfo_route_pane.depth,
+        )
+            .with_sync(lfo_route_pane.sync)
+    }
+}
+
+#[derive(Clone)]
 struct NoteTile {
     semitone: f32,
     start: f32,
     length: f32,
     envelope: Option<EnvelopePane>,
+    velocity: f32,
+    playback: Option<ScorePlayback>,
+    // Kept as loaded, since breakpoints are edited in the json file.
+    modulation: Option<ScoreModulation>,
+    bend: Option<BendPane>,
     is_property_displayed: bool
 }
 
 impl NoteTile {
-    fn new(semitone: f32, start: f32, length: f32, envelope: Option<EnvelopePane>) -> Self {
+    fn new(semitone: f32, start: f32, length: f32, envelope: Option<EnvelopePane>, velocity: f32,
+        playback: Option<ScorePlayback>, modulation: Option<ScoreModulation>) -> Self {
         Self {
             semitone,
             start,
             length,
             envelope,
+            velocity,
+            playback,
+            modulation,
+            bend: None,
             is_property_displayed: false,
         }
     }
+
+    fn with_bend(self, bend: Option<BendPane>) -> Self {
+        Self {
+            bend,
+            ..self
+        }
+    }
 }
 
 impl Pane for NoteTile {
@@ -156,6 +388,14 @@ impl Pane for NoteTile {
                             .range(0.0..=f32::INFINITY)
                         );
                     });
+                    ui.horizontal(|ui| {
+                        ui.label("velocity");
+                        ui.add(egui::DragValue::new(&mut self.velocity)
+                            .speed(0.01)
+                            .range(0.0..=1.0f32)
+                        );
+                    });
+                    playback_ui(ui, "Note Playback", &mut self.playback);
 
                     match self.envelope.as_mut() {
                         Some(envelope) => {
@@ -170,6 +410,36 @@ impl Pane for NoteTile {
                             }
                         },
                     }
+
+                    match self.bend.as_mut() {
+                        Some(bend) => {
+                            bend.ui(ui, ctx);
+                            if ui.button("Remove Bend").clicked() {
+                                self.bend = None;
+                            }
+                        },
+                        None => {
+                            if ui.button("Add Bend").clicked() {
+                                self.bend = Some(BendPane::default());
+                            }
+                        },
+                    }
+
+                    if let Some(modulation) = self.modulation.as_ref() {
+                        let targets: Vec<&str> = [
+                            ("Amplitude", modulation.amplitude().is_some()),
+                            ("Pitch", modulation.pitch().is_some()),
+                            ("Cutoff", modulation.cutoff().is_some()),
+                            ("Pan", modulation.pan().is_some()),
+                            ("LFO", modulation.lfos().is_some()),
+                        ].into_iter().filter(|(_, is_modulated)| *is_modulated).map(|(target, _)| target).collect();
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Modulation: {}", targets.join(", ")));
+                            if ui.button("Remove Modulation").clicked() {
+                                self.modulation = None;
+                            }
+                        });
+                    }
                 });
         }
     }
@@ -185,7 +455,11 @@ impl From<NoteTile> for ScoreNote {
                 Some(envelope) => Some(envelope.into()),
                 None => None,
             },
+            Some(note_tile.velocity),
+            note_tile.playback,
+            note_tile.modulation,
         )
+            .with_bend(note_tile.bend.map(|bend| bend.into()))
     }
 }
 
@@ -360,7 +634,12 @@ pub struct TrackPane {
     volume: f32,
     channel: u16,
     max_channel: u16,
+    downmix: Option<Downmix>,
     envelope: Option<EnvelopePane>,
+    lfos: Vec<LfoRoutePane>,
+    // Seconds of the glide, if notes glide.
+    glide_time: Option<f32>,
+    legato: bool,
 
     scale: Scale,
 
@@ -383,7 +662,11 @@ impl Default for TrackPane {
             volume: Self::DEFAULT_VOLUME,
             channel: Self::DEFAULT_CHANNEL,
             max_channel: Self::DEFAULT_CHANNEL+1,
+            downmix: None,
             envelope: None,
+            lfos: Vec::new(),
+            glide_time: None,
+            legato: false,
             scale: Self::DEFAULT_SCALE,
 
             semitone_height: Self::DEFAULT_SEMITONE_HEIGHT,
@@ -403,6 +686,7 @@ impl TrackPane {
     const DEFAULT_VOLUME: f32 = 1f32;
     const DEFAULT_CHANNEL: u16 = 0u16;
     const DEFAULT_SCALE: Scale = Scale::CMajor;
+    const DEFAULT_GLIDE_TIME: f32 = 0.1f32;
 
     const DEFAULT_SEMITONE_HEIGHT: f32 = 20f32;
     const DEFAULT_NUM_SEMITONE: f32 = 13f32;
@@ -445,7 +729,10 @@ impl TrackPane {
                         Some(envelope) => Some(envelope.into()),
                         None => None,
                     },
-                ));
+                    score_note.velocity().unwrap_or(DEFAULT_VELOCITY),
+                    score_note.playback(),
+                    score_note.modulation().clone(),
+                ).with_bend(score_note.bend().map(|bend| bend.into())));
             }
             start = start.max(current_start + score_note.length());
         }
@@ -456,6 +743,10 @@ impl TrackPane {
             volume,
             channel: score_part.channel(),
             max_channel,
+            downmix: score_part.downmix(),
+            lfos: score_part.lfos().iter().flatten().map(|lfo| lfo.into()).collect(),
+            glide_time: score_part.glide().map(|glide| glide.time()),
+            legato: score_part.glide().is_some_and(|glide| glide.legato()),
             ..Default::default()
         })
     }
@@ -473,11 +764,33 @@ impl Pane for TrackPane {
             ui.label("Channel");
             ui.add(egui::DragValue::new(&mut self.channel)
                 .range(0u16..=self.max_channel-1));
+            ui.label("Downmix");
+            egui::ComboBox::from_id_salt("Downmix")
+                .selected_text(downmix_to_str(self.downmix))
+                .show_ui(ui, |ui| {
+                    for downmix in [None, Some(Downmix::Mix), Some(Downmix::Left), Some(Downmix::Right)] {
+                        ui.selectable_value(&mut self.downmix, downmix, downmix_to_str(downmix));
+                    }
+                });
             ui.label("Beat Length");
             ui.add(egui::DragValue::new(&mut self.beat_move_unit)
                 .range(0f32..=f32::INFINITY));
         });
 
+        ui.horizontal(|ui| {
+            let mut is_gliding = self.glide_time.is_some();
+            if ui.checkbox(&mut is_gliding, "Glide").changed() {
+                self.glide_time = if is_gliding { Some(Self::DEFAULT_GLIDE_TIME) } else { None };
+            }
+            if let Some(glide_time) = self.glide_time.as_mut() {
+                ui.label("Time");
+                ui.add(egui::DragValue::new(glide_time)
+                    .speed(0.01)
+                    .range(0f32..=f32::INFINITY));
+                ui.checkbox(&mut self.legato, "Legato");
+            }
+        });
+
         match self.envelope.as_mut() {
             Some(envelope) => {
                 envelope.ui(ui, ctx);
@@ -492,6 +805,25 @@ impl Pane for TrackPane {
             },
         }
 
+        let mut delete_lfo_ids = vec![];
+        for (i, lfo) in self.lfos.iter_mut().enumerate() {
+            ui.push_id(i, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("LFO {}", i + 1));
+                    if ui.button("Remove LFO").clicked() {
+                        delete_lfo_ids.push(i);
+                    }
+                });
+                lfo.ui(ui, ctx);
+            });
+        }
+        for id in delete_lfo_ids.into_iter().rev() {
+            self.lfos.remove(id);
+        }
+        if ui.button("Add LFO").clicked() {
+            self.lfos.push(LfoRoutePane::default());
+        }
+
         ui.horizontal(|ui| {
             ui.label("Source");
             self.source.ui(ui, ctx);
@@ -599,7 +931,7 @@ impl Pane for TrackPane {
                     let note_pos = grid_region.get_snapped_grid_pos(
                         grid_region.display_to_true_position(pos)
                     );
-                    self.source_notes.push(NoteTile::new(note_pos.y, note_pos.x, self.beat_move_unit, None));
+                    self.source_notes.push(NoteTile::new(note_pos.y, note_pos.x, self.beat_move_unit, None, DEFAULT_VELOCITY, None, None));
                 }
             }
 
@@ -613,19 +945,34 @@ impl Pane for TrackPane {
     }
 }
 
+fn downmix_to_str(downmix: Option<Downmix>) -> &'static str {
+    match downmix {
+        None => "Auto",
+        Some(Downmix::Mix) => "Mix",
+        Some(Downmix::Left) => "Left",
+        Some(Downmix::Right) => "Right",
+    }
+}
+
 impl From<&TrackPane> for ScorePart {
     fn from(track_pane: &TrackPane) -> Self {
         let source = track_pane.source.source().to_owned();
         Self::new(
             source,
             track_pane.bpm,
-            track_pane.source_notes.iter().map(|&source_note| source_note.into()).collect::<Vec<ScoreNote>>(),
+            track_pane.source_notes.iter().map(|source_note| source_note.clone().into()).collect::<Vec<ScoreNote>>(),
             Some(track_pane.volume),
             Some(track_pane.channel),
             match track_pane.envelope {
                 Some(envelope) => Some(envelope.into()),
                 None => None,
             },
+            track_pane.downmix,
         )
+            .with_lfos(match track_pane.lfos.is_empty() {
+                true => None,
+                false => Some(track_pane.lfos.iter().map(|lfo| lfo.into()).collect()),
+            })
+            .with_glide(track_pane.glide_time.map(|glide_time| ScoreGlide::new(glide_time, Some(track_pane.legato))))
     }
 }
@@ -0,0 +1,212 @@
+use std::f32::consts::PI;
+
+use crate::utils::fft;
+
+
+const ONSET_FRAME_SECONDS: f32 = 0.02f32;
+const ONSET_OVERLAP: usize = 4;
+const ONSET_BLOCKS_PER_FRAME: usize = 16;
+const ONSET_COMPRESSION: f32 = 100f32;
+const ONSET_PEAK_RADIUS: usize = 2;
+const THRESHOLD_WINDOW_SECONDS: f32 = 0.1f32;
+// Height above the local mean of the normalized flux needed at a sensitivity of 0.
+const MAX_THRESHOLD: f32 = 0.2f32;
+const MIN_ONSET_INTERVAL_SECONDS: f32 = 0.05f32;
+
+const MIN_PITCH_FREQUENCY: f32 = 30f32;
+const MAX_PITCH_FREQUENCY: f32 = 4000f32;
+// Normalized difference below which a lag counts as a period.
+const YIN_THRESHOLD: f32 = 0.15f32;
+// Frames quieter than this ratio of the loudest one are left out of pitch detection.
+const PITCH_FRAME_MIN_LEVEL: f32 = 0.25f32;
+const MAX_PITCH_FRAMES: usize = 16;
+
+pub const DEFAULT_SENSITIVITY: f32 = 0.5f32;
+
+/// Increase of the log-compressed spectrum from one frame to the next, for frames centered every hop.
+fn spectral_flux(data: &[f32], frame: usize, hop: usize) -> Vec<f32> {
+    let window: Vec<f32> = (0..frame).map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / frame as f32).cos()).collect();
+    let mut re = vec![0f32; frame];
+    let mut im = vec![0f32; frame];
+    let mut previous = vec![0f32; frame / 2 + 1];
+    (0..data.len().div_ceil(hop)).map(|t| {
+        for (n, w) in window.iter().enumerate() {
+            let i = (t * hop + n).checked_sub(frame / 2);
+            re[n] = i.and_then(|i| data.get(i)).copied().unwrap_or(0f32) * w;
+            im[n] = 0f32;
+        }
+        fft(&mut re, &mut im, false);
+
+        let mut flux = 0f32;
+        for (bin, last) in previous.iter_mut().enumerate() {
+            let magnitude = (ONSET_COMPRESSION * re[bin].hypot(im[bin])).ln_1p();
+            flux += (magnitude - *last).max(0f32);
+            *last = magnitude;
+        }
+
+        flux
+    }).collect()
+}
+
+/// Start of the block around `center` whose energy rises the most from the previous one.
+fn refine_onset(data: &[f32], center: usize, frame: usize) -> usize {
+    let block = (frame / ONSET_BLOCKS_PER_FRAME).max(1);
+    let start = center.saturating_sub(frame / 2);
+    // The first difference favors the broadband attack over low notes still ringing.
+    let energy = |i: usize| data.windows(2).skip(i).take(block).map(|d| (d[1] - d[0]).powi(2)).sum::<f32>();
+    (start..(center + frame / 2).min(data.len())).step_by(block)
+        .map(|i| (i, energy(i) - if i >= block { energy(i - block) } else { 0f32 }))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(center, |(i, _)| i)
+}
+
+/// Frames at which transients start, from peaks of the spectral flux above its local mean.
+/// A `sensitivity` from 0.0 to 1.0 lowers the threshold, so that softer transients are found.
+pub fn detect_onsets(data: &[f32], sample_rate: u32, sensitivity: f32) -> Vec<usize> {
+    let frame = ((ONSET_FRAME_SECONDS * sample_rate as f32) as usize).next_power_of_two().max(ONSET_OVERLAP);
+    let hop = frame / ONSET_OVERLAP;
+    let mut flux = spectral_flux(data, frame, hop);
+    // The first frame rises from silence.
+    if let Some(first) = flux.first_mut() {
+        *first = 0f32;
+    }
+    let max = flux.iter().fold(0f32, |a, &f| a.max(f));
+    if max <= 0f32 {
+        return vec![];
+    }
+    for f in flux.iter_mut() {
+        *f /= max;
+    }
+
+    let radius = ((THRESHOLD_WINDOW_SECONDS * sample_rate as f32) as usize / hop).max(1);
+    let delta = MAX_THRESHOLD * (1f32 - sensitivity.clamp(0f32, 1f32));
+    let min_interval = (MIN_ONSET_INTERVAL_SECONDS * sample_rate as f32) as usize;
+    let mut onsets: Vec<usize> = vec![];
+    for t in 0..flux.len() {
+        let around = |r: usize| &flux[t.saturating_sub(r)..(t + r + 1).min(flux.len())];
+        let mean = around(radius).iter().sum::<f32>() / around(radius).len() as f32;
+        let is_peak = around(ONSET_PEAK_RADIUS).iter().all(|&f| f <= flux[t]);
+        if !is_peak || flux[t] <= mean + delta {
+            continue;
+        }
+        let onset = refine_onset(data, t * hop, frame);
+        if onsets.last().is_none_or(|&last| onset >= last + min_interval) {
+            onsets.push(onset);
+        }
+    }
+
+    onsets
+}
+
+/// Starts of the slices cut at every onset. The first slice always starts at frame 0,
+/// and onsets too close to either end of the data are left out.
+pub fn detect_slices(data: &[f32], sample_rate: u32, sensitivity: f32) -> Vec<usize> {
+    let min_interval = (MIN_ONSET_INTERVAL_SECONDS * sample_rate as f32) as usize;
+    let mut slices = vec![0];
+    slices.extend(detect_onsets(data, sample_rate, sensitivity).into_iter()
+        .filter(|&onset| min_interval <= onset && onset + min_interval <= data.len()));
+
+    slices
+}
+
+/// Period in frames of `frame` by YIN: the first dip of the cumulative mean normalized
+/// difference below the threshold, refined between frames by a parabola.
+fn yin_period(frame: &[f32], min_lag: usize, max_lag: usize) -> Option<f32> {
+    let window = frame.len() - max_lag;
+    let difference: Vec<f32> = (0..=max_lag).map(|lag| {
+        (0..window).map(|j| (frame[j] - frame[j + lag]).powi(2)).sum::<f32>()
+    }).collect();
+
+    let mut normalized = vec![1f32; max_lag + 1];
+    let mut sum = 0f32;
+    for lag in 1..=max_lag {
+        sum += difference[lag];
+        normalized[lag] = if sum > 0f32 { difference[lag] * lag as f32 / sum } else { 1f32 };
+    }
+
+    let mut lag = (min_lag.max(1)..max_lag).find(|&lag| normalized[lag] < YIN_THRESHOLD)?;
+    while lag + 1 < max_lag && normalized[lag + 1] < normalized[lag] {
+        lag += 1;
+    }
+    let (a, b, c) = (normalized[lag - 1], normalized[lag], normalized[lag + 1]);
+    let curvature = a - 2f32 * b + c;
+    let shift = if curvature > 0f32 { (a - c) / (2f32 * curvature) } else { 0f32 };
+
+    Some(lag as f32 + shift.clamp(-1f32, 1f32))
+}
+
+/// Fundamental frequency of `data` in Hz, the median of the YIN estimates of its loudest frames.
+/// None if no frame is periodic.
+pub fn detect_pitch(data: &[f32], sample_rate: u32) -> Option<f32> {
+    let min_lag = (sample_rate as f32 / MAX_PITCH_FREQUENCY) as usize;
+    let max_lag = (sample_rate as f32 / MIN_PITCH_FREQUENCY) as usize;
+    // The difference is summed over a window as long as the longest period.
+    let frame_len = 2 * max_lag;
+    if data.len() < frame_len {
+        return None;
+    }
+
+    let levels: Vec<(usize, f32)> = (0..=data.len() - frame_len).step_by(max_lag)
+        .map(|start| (start, data[start..start + frame_len].iter().map(|d| d * d).sum::<f32>()))
+        .collect();
+    let max_level = levels.iter().fold(0f32, |a, &(_, level)| a.max(level));
+    if max_level <= 0f32 {
+        return None;
+    }
+    let loud: Vec<usize> = levels.into_iter()
+        .filter(|&(_, level)| level >= PITCH_FRAME_MIN_LEVEL * max_level)
+        .map(|(start, _)| start)
+        .collect();
+
+    let step = loud.len().div_ceil(MAX_PITCH_FRAMES);
+    let mut periods: Vec<f32> = loud.iter().step_by(step)
+        .filter_map(|&start| yin_period(&data[start..start + frame_len], min_lag, max_lag))
+        .collect();
+    if periods.is_empty() {
+        return None;
+    }
+    periods.sort_unstable_by(|a, b| a.total_cmp(b));
+
+    Some(sample_rate as f32 / periods[periods.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 44_100;
+
+    fn cents(frequency: f32, expected: f32) -> f32 {
+        1200f32 * (frequency / expected).log2()
+    }
+
+    #[test]
+    fn detects_sine_pitch() {
+        let sine: Vec<f32> = (0..SAMPLE_RATE)
+            .map(|n| (2f32 * PI * 440f32 * n as f32 / SAMPLE_RATE as f32).sin())
+            .collect();
+
+        assert!(cents(detect_pitch(&sine, SAMPLE_RATE).unwrap(), 440f32).abs() < 5f32);
+    }
+
+    #[test]
+    fn detects_saw_pitch() {
+        let saw: Vec<f32> = (0..SAMPLE_RATE)
+            .map(|n| 2f32 * (440f32 * n as f32 / SAMPLE_RATE as f32).fract() - 1f32)
+            .collect();
+
+        assert!(cents(detect_pitch(&saw, SAMPLE_RATE).unwrap(), 440f32).abs() < 5f32);
+    }
+
+    #[test]
+    fn silence_and_noise_have_no_pitch() {
+        let mut state = 1u32;
+        let noise: Vec<f32> = (0..SAMPLE_RATE).map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            state as f32 / u32::MAX as f32 * 2f32 - 1f32
+        }).collect();
+
+        assert!(detect_pitch(&vec![0f32; SAMPLE_RATE as usize], SAMPLE_RATE).is_none());
+        assert!(detect_pitch(&noise, SAMPLE_RATE).is_none());
+    }
+}
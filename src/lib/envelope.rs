@@ -1,12 +1,32 @@
 use crate::note::Note;
+use crate::score::EnvelopeCurve;
 
 
+// Time constants an exponential segment spans, which leaves it within 1% of its target.
+const EXPONENTIAL_STEEPNESS: f32 = 5f32;
+
+/// Part of a segment's change done after `x` of its time, from 0.0 to 1.0.
+fn shape(curve: EnvelopeCurve, x: f32) -> f32 {
+    let x = x.clamp(0f32, 1f32);
+    match curve {
+        EnvelopeCurve::Linear => x,
+        EnvelopeCurve::Exponential => (1f32 - (-EXPONENTIAL_STEEPNESS * x).exp()) / (1f32 - (-EXPONENTIAL_STEEPNESS).exp()),
+        EnvelopeCurve::Logarithmic => ((EXPONENTIAL_STEEPNESS * x).exp() - 1f32) / (EXPONENTIAL_STEEPNESS.exp() - 1f32),
+        EnvelopeCurve::Power(power) => x.powf(power.max(0f32)),
+    }
+}
+
 #[derive(Clone)]
 pub struct Envelope {
     attack: f32,
     decay: f32,
     sustain: f32,
     release: f32,
+    delay: f32,
+    hold: f32,
+    attack_curve: EnvelopeCurve,
+    decay_curve: EnvelopeCurve,
+    release_curve: EnvelopeCurve,
 }
 
 impl Envelope {
@@ -16,25 +36,192 @@ impl Envelope {
             decay,
             sustain,
             release,
+            delay: 0f32,
+            hold: 0f32,
+            attack_curve: EnvelopeCurve::Linear,
+            decay_curve: EnvelopeCurve::Linear,
+            release_curve: EnvelopeCurve::Linear,
         }
     }
 
-    pub fn apply(&self, note: Note) -> Note {
-        let mut data = note.data().iter().enumerate().map(|(i, &d)| {
-            let t = i as f32 / note.sample_rate() as f32;
-            d * if t < self.attack {
-                t / self.attack
-            } else if t < self.attack + self.decay {
-                1f32 + (self.sustain - 1f32) * (t - self.attack) / self.decay
-            } else {
-                self.sustain
-            }
-        }).collect::<Vec<_>>();
-        for i in 0..(note.sample_rate() as f32 * self.release) as usize {
-            let d = note.data()[i as usize % note.data().len()];
-            data.push(d * self.sustain);
+    pub fn with_delay(self, delay: f32) -> Self {
+        Self {
+            delay,
+            ..self
         }
+    }
+
+    pub fn with_hold(self, hold: f32) -> Self {
+        Self {
+            hold,
+            ..self
+        }
+    }
+
+    pub fn with_curves(self, attack_curve: EnvelopeCurve, decay_curve: EnvelopeCurve, release_curve: EnvelopeCurve) -> Self {
+        Self {
+            attack_curve,
+            decay_curve,
+            release_curve,
+            ..self
+        }
+    }
+
+    /// Seconds to fade out after note-off.
+    pub fn release(&self) -> f32 {
+        self.release
+    }
+
+    /// Gain at `t` seconds after note-on, before release.
+    pub fn level(&self, t: f32) -> f32 {
+        let t = t - self.delay;
+        if t < 0f32 {
+            0f32
+        } else if t < self.attack {
+            shape(self.attack_curve, t / self.attack)
+        } else if t < self.attack + self.hold {
+            1f32
+        } else if t < self.attack + self.hold + self.decay {
+            1f32 + (self.sustain - 1f32) * shape(self.decay_curve, (t - self.attack - self.hold) / self.decay)
+        } else {
+            self.sustain
+        }
+    }
+
+    /// Gain at `t` seconds after note-on for a note released `note_off` seconds after it.
+    /// The release fades from the level reached at note-off.
+    pub fn value(&self, t: f32, note_off: f32) -> f32 {
+        if t < note_off {
+            self.level(t)
+        } else if t < note_off + self.release {
+            self.level(note_off) * (1f32 - shape(self.release_curve, (t - note_off) / self.release))
+        } else {
+            0f32
+        }
+    }
+
+    /// Shapes the note released `note_off` seconds after note-on. The release fades from the level
+    /// reached at note-off to silence, over the note's own sound past note-off, which is
+    /// silent where the note is shorter.
+    pub fn apply(&self, note: Note, note_off: f32) -> Note {
+        let sample_rate = note.sample_rate() as f32;
+        let off = (note_off * sample_rate) as usize;
+        let release = (self.release * sample_rate) as usize;
+        let off_level = self.level(note_off);
+
+        note.map_channels(|channel| {
+            (0..off + release).map(|i| {
+                let d = channel.get(i).copied().unwrap_or(0f32);
+                if i < off {
+                    d * self.level(i as f32 / sample_rate)
+                } else {
+                    d * off_level * (1f32 - shape(self.release_curve, (i - off) as f32 / release as f32))
+                }
+            }).collect()
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct Breakpoint {
+    time: f32,
+    level: f32,
+    curve: EnvelopeCurve,
+}
 
-        Note::new(data, note.sample_rate(), note.envelope().clone())
+impl Breakpoint {
+    /// The point reaches `level` at `time` seconds, along `curve` from the previous point.
+    pub fn new(time: f32, level: f32, curve: EnvelopeCurve) -> Self {
+        Self {
+            time,
+            level,
+            curve,
+        }
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+}
+
+/// Levels going through points in time order. The envelope can stop at a sustain point,
+/// or go round from a loop point back to it, until note-off.
+#[derive(Clone)]
+pub struct BreakpointEnvelope {
+    points: Vec<Breakpoint>,
+    sustain: Option<usize>,
+    loop_start: Option<usize>,
+}
+
+impl BreakpointEnvelope {
+    /// A sustain point outside `points`, or a loop point not before it, is ignored.
+    pub fn new(points: Vec<Breakpoint>, sustain: Option<usize>, loop_start: Option<usize>) -> Self {
+        let sustain = sustain.filter(|&sustain| sustain < points.len());
+        let loop_start = loop_start.filter(|&loop_start| sustain.is_some_and(|sustain| loop_start < sustain));
+
+        Self {
+            points,
+            sustain,
+            loop_start,
+        }
+    }
+
+    /// Seconds from the sustain point to the last one, which play after note-off.
+    pub fn release(&self) -> f32 {
+        match (self.sustain, self.points.last()) {
+            (Some(sustain), Some(last)) => (last.time - self.points[sustain].time).max(0f32),
+            _ => 0f32,
+        }
+    }
+
+    /// Level at `time` on the timeline of the points. The segment leaving point `from.0`
+    /// starts at level `from.1` rather than at the point's own level.
+    fn level_at(&self, time: f32, from: Option<(usize, f32)>) -> f32 {
+        let Some(first) = self.points.first() else {
+            return 0f32;
+        };
+        if time <= first.time {
+            return first.level;
+        }
+
+        match self.points.iter().position(|point| point.time > time) {
+            Some(next) => {
+                let (previous, point) = (&self.points[next - 1], &self.points[next]);
+                let start = match from {
+                    Some((index, level)) if index == next - 1 => level,
+                    _ => previous.level,
+                };
+                let x = (time - previous.time) / (point.time - previous.time);
+                start + (point.level - start) * shape(point.curve, x)
+            },
+            None => self.points[self.points.len() - 1].level,
+        }
+    }
+
+    /// Time on the timeline of the points `t` seconds after note-on, while the note is held.
+    fn held_time(&self, t: f32) -> f32 {
+        let Some(sustain) = self.sustain else {
+            return t;
+        };
+        let sustain_time = self.points[sustain].time;
+        match self.loop_start.map(|loop_start| self.points[loop_start].time) {
+            Some(loop_time) if t > sustain_time && sustain_time > loop_time => {
+                loop_time + (t - sustain_time) % (sustain_time - loop_time)
+            },
+            _ => t.min(sustain_time),
+        }
+    }
+
+    /// Level at `t` seconds after note-on for a note released `note_off` seconds after it.
+    /// After note-off, the points following the sustain one are played from the level reached.
+    /// Without a sustain point, the envelope ignores note-off.
+    pub fn value(&self, t: f32, note_off: f32) -> f32 {
+        match self.sustain {
+            Some(sustain) if t > note_off => {
+                let off_level = self.level_at(self.held_time(note_off), None);
+                self.level_at(self.points[sustain].time + t - note_off, Some((sustain, off_level)))
+            },
+            _ => self.level_at(self.held_time(t), None),
+        }
     }
 }
@@ -25,3 +25,24 @@ impl Display for IndexError {
 }
 
 impl Error for IndexError {}
+
+#[derive(Debug)]
+pub struct SourceError {
+    message: String,
+}
+
+impl SourceError {
+    pub fn new(message: String) -> Self {
+        Self {
+            message,
+        }
+    }
+}
+
+impl Display for SourceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid sound source: {}", self.message)
+    }
+}
+
+impl Error for SourceError {}
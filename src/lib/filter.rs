@@ -0,0 +1,100 @@
+use std::f32::consts::PI;
+
+
+/// Second order IIR filter with coefficients from the RBJ audio EQ cookbook.
+#[derive(Clone)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    const MAX_FREQUENCY_RATE: f32 = 0.49f32;
+
+    fn new(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            x1: 0f32,
+            x2: 0f32,
+            y1: 0f32,
+            y2: 0f32,
+        }
+    }
+
+    fn omega(frequency: f32, sample_rate: u32) -> f32 {
+        let frequency = frequency.clamp(1f32, sample_rate as f32 * Self::MAX_FREQUENCY_RATE);
+        2.0 * PI * frequency / sample_rate as f32
+    }
+
+    /// Band-pass with a peak gain of 0 dB at `frequency`.
+    pub fn band_pass(frequency: f32, q: f32, sample_rate: u32) -> Self {
+        let omega = Self::omega(frequency, sample_rate);
+        let alpha = omega.sin() / (2.0 * q.max(f32::EPSILON));
+
+        Self::new(alpha, 0f32, -alpha, 1f32 + alpha, -2.0 * omega.cos(), 1f32 - alpha)
+    }
+
+    pub fn high_pass(frequency: f32, q: f32, sample_rate: u32) -> Self {
+        let omega = Self::omega(frequency, sample_rate);
+        let alpha = omega.sin() / (2.0 * q.max(f32::EPSILON));
+        let cos = omega.cos();
+
+        Self::new((1f32 + cos) / 2.0, -1f32 - cos, (1f32 + cos) / 2.0, 1f32 + alpha, -2.0 * cos, 1f32 - alpha)
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2 - self.a1 * self.y1 - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+
+        y
+    }
+}
+
+/// Topology-preserving state variable filter whose cutoff can change every sample.
+#[derive(Clone, Default)]
+pub struct StateVariableFilter {
+    ic1eq: f32,
+    ic2eq: f32,
+}
+
+impl StateVariableFilter {
+    const MAX_FREQUENCY_RATE: f32 = 0.49f32;
+    const MAX_RESONANCE: f32 = 0.99f32;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the low-pass, band-pass and high-pass outputs for `x`.
+    /// `resonance` goes from 0.0 (no peak) to just below 1.0 (self-oscillation).
+    pub fn process(&mut self, x: f32, cutoff: f32, resonance: f32, sample_rate: u32) -> (f32, f32, f32) {
+        let cutoff = cutoff.clamp(1f32, sample_rate as f32 * Self::MAX_FREQUENCY_RATE);
+        let g = (PI * cutoff / sample_rate as f32).tan();
+        let k = 2f32 - 2f32 * resonance.clamp(0f32, Self::MAX_RESONANCE);
+        let a1 = 1f32 / (1f32 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        let v3 = x - self.ic2eq;
+        let v1 = a1 * self.ic1eq + a2 * v3;
+        let v2 = self.ic2eq + a2 * self.ic1eq + a3 * v3;
+        self.ic1eq = 2f32 * v1 - self.ic1eq;
+        self.ic2eq = 2f32 * v2 - self.ic2eq;
+
+        (v2, v1, x - k * v1 - v2)
+    }
+}
@@ -0,0 +1,114 @@
+use std::f32::consts::PI;
+
+use crate::score::{LfoShape, LfoTarget, ScoreLfo};
+
+
+/// Random value in [-1, 1] held through the `cycle`-th cycle, the same for every note.
+fn held_value(cycle: u64) -> f32 {
+    // SplitMix64 finalizer, so that neighboring cycles are unrelated.
+    let mut x = cycle.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^= x >> 31;
+
+    (x >> 40) as f32 / (1u64 << 24) as f32 * 2f32 - 1f32
+}
+
+#[derive(Clone)]
+pub struct Lfo {
+    shape: LfoShape,
+    rate: f32,
+    delay: f32,
+    fade_in: f32,
+}
+
+impl Lfo {
+    pub fn new(shape: LfoShape, rate: f32) -> Self {
+        Self {
+            shape,
+            rate,
+            delay: 0f32,
+            fade_in: 0f32,
+        }
+    }
+
+    pub fn with_rate(self, rate: f32) -> Self {
+        Self {
+            rate,
+            ..self
+        }
+    }
+
+    pub fn with_delay(self, delay: f32) -> Self {
+        Self {
+            delay,
+            ..self
+        }
+    }
+
+    pub fn with_fade_in(self, fade_in: f32) -> Self {
+        Self {
+            fade_in,
+            ..self
+        }
+    }
+
+    /// Value in [-1, 1] at `t` seconds after note-on. The LFO starts from phase 0 after its delay
+    /// and reaches its full depth after fading in.
+    pub fn value(&self, t: f32) -> f32 {
+        let t = t - self.delay;
+        if t < 0f32 {
+            return 0f32;
+        }
+        let depth = if self.fade_in > 0f32 { (t / self.fade_in).min(1f32) } else { 1f32 };
+
+        let phase = (t * self.rate).fract();
+        depth * match self.shape {
+            LfoShape::Sine => (2.0 * PI * phase).sin(),
+            LfoShape::Triangle => 1f32 - 4f32 * ((phase + 0.25).fract() - 0.5).abs(),
+            LfoShape::Square => if phase < 0.5 { 1f32 } else { -1f32 },
+            LfoShape::Saw => 2f32 * phase - 1f32,
+            LfoShape::SampleAndHold => held_value((t * self.rate) as u64),
+        }
+    }
+}
+
+impl From<&ScoreLfo> for Lfo {
+    fn from(score_lfo: &ScoreLfo) -> Self {
+        Self::new(score_lfo.shape(), score_lfo.rate())
+            .with_delay(score_lfo.delay().unwrap_or(0f32))
+            .with_fade_in(score_lfo.fade_in().unwrap_or(0f32))
+    }
+}
+
+/// An LFO moving one parameter of a note by up to `depth`.
+#[derive(Clone)]
+pub struct LfoRoute {
+    lfo: Lfo,
+    target: LfoTarget,
+    depth: f32,
+}
+
+impl LfoRoute {
+    pub fn new(lfo: Lfo, target: LfoTarget, depth: f32) -> Self {
+        Self {
+            lfo,
+            target,
+            depth,
+        }
+    }
+
+    pub fn target(&self) -> LfoTarget {
+        self.target
+    }
+
+    /// Offset of the target at `t` seconds after note-on.
+    pub fn value(&self, t: f32) -> f32 {
+        self.depth * self.lfo.value(t)
+    }
+}
+
+/// Sum of the offsets of the routes to `target` at `t` seconds after note-on.
+pub fn lfo_offset(lfos: &[LfoRoute], target: LfoTarget, t: f32) -> f32 {
+    lfos.iter().filter(|lfo| lfo.target() == target).map(|lfo| lfo.value(t)).sum()
+}
@@ -1,8 +1,13 @@
+pub mod analysis;
 pub mod envelope;
 pub mod error;
+pub mod filter;
+pub mod lfo;
+pub mod modulation;
 pub mod source;
 pub mod note;
 pub mod player;
 pub mod score;
+pub mod stretch;
 pub mod track;
 pub mod utils;
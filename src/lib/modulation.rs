@@ -0,0 +1,172 @@
+use std::f32::consts::PI;
+
+use crate::envelope::BreakpointEnvelope;
+use crate::filter::StateVariableFilter;
+use crate::lfo::{lfo_offset, LfoRoute};
+use crate::note::Note;
+use crate::score::LfoTarget;
+
+
+/// Breakpoint envelopes and LFOs changing the amplitude, pitch, filter cutoff and pan of a rendered note.
+pub struct Modulation {
+    amplitude: Option<BreakpointEnvelope>,
+    pitch: Option<BreakpointEnvelope>,
+    cutoff: Option<BreakpointEnvelope>,
+    pan: Option<BreakpointEnvelope>,
+    lfos: Vec<LfoRoute>,
+    bend: Option<BreakpointEnvelope>,
+}
+
+impl Modulation {
+    pub fn new(amplitude: Option<BreakpointEnvelope>, pitch: Option<BreakpointEnvelope>,
+        cutoff: Option<BreakpointEnvelope>, pan: Option<BreakpointEnvelope>) -> Self {
+        Self {
+            amplitude,
+            pitch,
+            cutoff,
+            pan,
+            lfos: vec![],
+            bend: None,
+        }
+    }
+
+    pub fn with_lfos(self, lfos: Vec<LfoRoute>) -> Self {
+        Self {
+            lfos,
+            ..self
+        }
+    }
+
+    /// Semitones of bends and glides, added to the pitch envelope.
+    pub fn with_bend(self, bend: Option<BreakpointEnvelope>) -> Self {
+        Self {
+            bend,
+            ..self
+        }
+    }
+
+    fn lfo(&self, target: LfoTarget, t: f32) -> f32 {
+        lfo_offset(&self.lfos, target, t)
+    }
+
+    fn has_lfo(&self, target: LfoTarget) -> bool {
+        self.lfos.iter().any(|lfo| lfo.target() == target)
+    }
+
+    /// Semitones added to the note at `t` seconds after note-on, if its pitch is modulated.
+    fn pitch_at(&self, t: f32, note_off: f32) -> Option<f32> {
+        match (self.pitch.as_ref(), self.bend.as_ref(), self.has_lfo(LfoTarget::Pitch)) {
+            (None, None, false) => None,
+            (pitch, bend, _) => Some(pitch.map_or(0f32, |pitch| pitch.value(t, note_off))
+                + bend.map_or(0f32, |bend| bend.value(t, note_off))
+                + self.lfo(LfoTarget::Pitch, t)),
+        }
+    }
+
+    /// Seconds the amplitude envelope keeps the note sounding after note-off.
+    pub fn release(&self) -> f32 {
+        self.amplitude.as_ref().map_or(0f32, |amplitude| amplitude.release())
+    }
+
+    /// Positions in the unmodulated note read for each of `length` frames at the modulated pitch.
+    /// None if the pitch is not modulated.
+    fn read_positions(&self, length: usize, note_off: f32, sample_rate: u32) -> Option<Vec<f32>> {
+        self.pitch_at(0f32, note_off)?;
+
+        let mut position = 0f32;
+        Some((0..length).map(|i| {
+            let read = position;
+            let semitones = self.pitch_at(i as f32 / sample_rate as f32, note_off).unwrap_or(0f32);
+            position += 2f32.powf(semitones / 12f32);
+            read
+        }).collect())
+    }
+
+    /// Seconds of the unmodulated note needed to play `seconds` of the modulated one.
+    pub fn source_seconds(&self, seconds: f32, note_off: f32, sample_rate: u32) -> f32 {
+        let length = (seconds * sample_rate as f32) as usize;
+        match self.read_positions(length, note_off, sample_rate) {
+            Some(positions) => {
+                let last = positions.last().copied().unwrap_or(0f32);
+                // The frame after the last position is read for interpolation.
+                (last + 2f32) / sample_rate as f32
+            },
+            None => seconds,
+        }
+    }
+
+    /// Modulates a note released `note_off` seconds after its start. The pitch envelope
+    /// reads the note faster or slower into `seconds` of sound, so the note must be
+    /// `source_seconds` long.
+    pub fn apply(&self, note: Note, seconds: f32, note_off: f32) -> Note {
+        let sample_rate = note.sample_rate();
+        let time = |i: usize| i as f32 / sample_rate as f32;
+
+        let note = match self.read_positions((seconds * sample_rate as f32) as usize, note_off, sample_rate) {
+            Some(positions) => {
+                note.map_channels(|data| positions.iter().map(|&position| {
+                    let i = position as usize;
+                    let frac = position - i as f32;
+                    let d0 = data.get(i).copied().unwrap_or(0f32);
+                    let d1 = data.get(i + 1).copied().unwrap_or(0f32);
+                    d0 + (d1 - d0) * frac
+                }).collect())
+            },
+            None => note,
+        };
+
+        let note = match self.cutoff.as_ref() {
+            Some(cutoff) => note.map_channels(|data| {
+                let mut filter = StateVariableFilter::new();
+                data.iter().enumerate()
+                    .map(|(i, &d)| {
+                        let frequency = cutoff.value(time(i), note_off) * 2f32.powf(self.lfo(LfoTarget::Cutoff, time(i)));
+                        filter.process(d, frequency, 0f32, sample_rate).0
+                    })
+                    .collect()
+            }),
+            None => note,
+        };
+
+        let note = match (self.amplitude.as_ref(), self.has_lfo(LfoTarget::Amplitude)) {
+            (None, false) => note,
+            (amplitude, _) => note.map_channels(|data| {
+                data.iter().enumerate().map(|(i, &d)| {
+                    let gain = amplitude.map_or(1f32, |amplitude| amplitude.value(time(i), note_off))
+                        * (1f32 + self.lfo(LfoTarget::Amplitude, time(i))).max(0f32);
+                    d * gain
+                }).collect()
+            }),
+        };
+
+        match (self.pan.as_ref(), self.has_lfo(LfoTarget::Pan)) {
+            (None, false) => note,
+            (pan, _) => {
+                let pan_at = |i: usize| {
+                    let pan = pan.map_or(0f32, |pan| pan.value(time(i), note_off)) + self.lfo(LfoTarget::Pan, time(i));
+                    pan.clamp(-1f32, 1f32)
+                };
+                let channels = match note.channels().len() {
+                    // A mono note is spread at constant power.
+                    1 => {
+                        let data = note.data();
+                        let (left, right) = data.iter().enumerate().map(|(i, &d)| {
+                            let (sin, cos) = ((pan_at(i) + 1f32) * PI / 4f32).sin_cos();
+                            (d * cos, d * sin)
+                        }).unzip();
+                        vec![left, right]
+                    },
+                    // Wider notes keep their image, with the far side turned down.
+                    _ => note.channels().iter().enumerate().map(|(channel, data)| {
+                        data.iter().enumerate().map(|(i, &d)| match channel {
+                            0 => d * (1f32 - pan_at(i)).min(1f32),
+                            1 => d * (1f32 + pan_at(i)).min(1f32),
+                            _ => d,
+                        }).collect()
+                    }).collect(),
+                };
+                Note::from_channels(channels, sample_rate, note.envelope().clone())
+            },
+        }
+    }
+}
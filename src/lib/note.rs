@@ -3,7 +3,7 @@ use crate::envelope::Envelope;
 
 #[derive(Clone)]
 pub struct Note {
-    data: Vec<f32>,
+    channels: Vec<Vec<f32>>,
     sample_rate: u32,
     envelope: Option<Envelope>,
 }
@@ -11,14 +11,40 @@ pub struct Note {
 impl Note {
     pub fn new(data: Vec<f32>, sample_rate: u32, envelope: Option<Envelope>) -> Self {
         Note {
-            data,
+            channels: vec![data],
             sample_rate,
             envelope,
         }
     }
 
+    pub fn from_channels(channels: Vec<Vec<f32>>, sample_rate: u32, envelope: Option<Envelope>) -> Self {
+        if channels.is_empty() {
+            return Self::new(vec![], sample_rate, envelope);
+        }
+
+        Note {
+            channels,
+            sample_rate,
+            envelope,
+        }
+    }
+
+    /// The first channel, which is the whole note for mono notes.
     pub fn data(&self) -> &Vec<f32> {
-        &self.data
+        &self.channels[0]
+    }
+
+    pub fn channels(&self) -> &Vec<Vec<f32>> {
+        &self.channels
+    }
+
+    /// Applies `f` to every channel, keeping the sample rate and envelope.
+    pub fn map_channels(&self, f: impl Fn(&Vec<f32>) -> Vec<f32>) -> Note {
+        Note {
+            channels: self.channels.iter().map(f).collect(),
+            sample_rate: self.sample_rate,
+            envelope: self.envelope.clone(),
+        }
     }
 
     pub fn sample_rate(&self) -> u32 {
@@ -28,4 +54,11 @@ impl Note {
     pub fn envelope(&self) -> &Option<Envelope> {
         &self.envelope
     }
+
+    pub fn with_envelope(self, envelope: Option<Envelope>) -> Self {
+        Self {
+            envelope,
+            ..self
+        }
+    }
 }
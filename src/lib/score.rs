@@ -3,51 +3,1047 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
-use crate::envelope::Envelope;
+use crate::envelope::{Breakpoint, BreakpointEnvelope, Envelope};
+use crate::lfo::{Lfo, LfoRoute};
+use crate::modulation::Modulation;
+use crate::source::additive::Additive;
+use crate::source::drum_synth::DrumSynth;
+use crate::source::fm::Fm;
+use crate::source::granular::Granular;
 use crate::source::karplus_strong::KarpusStrong;
-use crate::source::SoundSource;
+use crate::source::modal::Modal;
+use crate::source::multi_sampler::MultiSampler;
+use crate::source::noise::Noise;
+use crate::source::{DEFAULT_VELOCITY, NoteRequest, SoundSource};
+use crate::source::pulse::Pulse;
 use crate::source::sampler::Sampler;
+use crate::source::saw::Saw;
+use crate::source::sfz::Sfz;
 use crate::source::sin::Sin;
+use crate::source::sliced_sampler::SlicedSampler;
+use crate::source::sound_font::SoundFont;
+use crate::source::square::Square;
+use crate::source::subtractive::Subtractive;
 use crate::source::triangle::Triangle;
+use crate::source::wavetable::Wavetable;
 use crate::track::{InstrumentTrack, Mixdown};
 
 const DEFAULT_VOLUME: f32 = 1.0f32;
 
 
+#[derive(Clone, Deserialize, Serialize)]
+pub enum WavetableFrames {
+    File {
+        path: PathBuf,
+        frame_size: Option<usize>,
+    },
+    Basic,
+}
+
+/// Operator routing of an `Fm` source. Operator 0 is the first operator, and an
+/// operator can only modulate operators with a lower index.
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+pub enum FmAlgorithm {
+    Stack,
+    Parallel,
+    Pairs,
+    Branch,
+    Custom {
+        routes: Vec<(usize, usize)>,
+        carriers: Vec<usize>,
+    },
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreFmOperator {
+    ratio: f32,
+    level: f32,
+    feedback: Option<f32>,
+    envelope: Option<ScoreEnvelope>,
+}
+
+impl ScoreFmOperator {
+    pub fn new(ratio: f32, level: f32, feedback: Option<f32>, envelope: Option<ScoreEnvelope>) -> Self {
+        Self {
+            ratio,
+            level,
+            feedback,
+            envelope,
+        }
+    }
+
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    pub fn feedback(&self) -> Option<f32> {
+        self.feedback
+    }
+
+    pub fn envelope(&self) -> &Option<ScoreEnvelope> {
+        &self.envelope
+    }
+}
+
+/// A sine partial of an `Additive` source.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScorePartial {
+    harmonic: f32,
+    amplitude: f32,
+    detune: Option<f32>,
+    phase: Option<f32>,
+}
+
+impl ScorePartial {
+    pub fn new(harmonic: f32, amplitude: f32, detune: Option<f32>, phase: Option<f32>) -> Self {
+        Self {
+            harmonic,
+            amplitude,
+            detune,
+            phase,
+        }
+    }
+
+    pub fn harmonic(&self) -> f32 {
+        self.harmonic
+    }
+
+    pub fn amplitude(&self) -> f32 {
+        self.amplitude
+    }
+
+    /// Detune in cents.
+    pub fn detune(&self) -> Option<f32> {
+        self.detune
+    }
+
+    /// Initial phase in cycles.
+    pub fn phase(&self) -> Option<f32> {
+        self.phase
+    }
+}
+
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+pub enum NoiseColor {
+    White,
+    Pink,
+    Brown,
+}
+
+/// A synthesized drum of a `DrumSynth` kit. Frequencies are in Hz and times in seconds.
+#[derive(Clone, Deserialize, Serialize)]
+pub enum DrumVoice {
+    Kick {
+        frequency: f32,
+        sweep: f32,
+        sweep_time: f32,
+        decay: f32,
+    },
+    Snare {
+        frequency: f32,
+        noise: f32,
+        decay: f32,
+    },
+    HiHat {
+        decay: f32,
+    },
+    Clap {
+        decay: f32,
+    },
+    Tom {
+        frequency: f32,
+        sweep: f32,
+        decay: f32,
+    },
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreDrumVoice {
+    key: i32,
+    voice: DrumVoice,
+}
+
+impl ScoreDrumVoice {
+    pub fn new(key: i32, voice: DrumVoice) -> Self {
+        Self {
+            key,
+            voice,
+        }
+    }
+
+    pub fn key(&self) -> i32 {
+        self.key
+    }
+
+    pub fn voice(&self) -> &DrumVoice {
+        &self.voice
+    }
+}
+
+/// How a `Sampler` fills a note.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum PlaybackMode {
+    /// Plays the whole sample, ignoring the note's length.
+    OneShot,
+    /// Repeats the loop, or the whole sample without one, crossfading at the seam.
+    Loop,
+    /// Plays the loop, or the whole sample without one, back and forth.
+    PingPong,
+    /// Stretches the whole sample to the note's length with the sampler's engine.
+    Stretch,
+}
+
+/// How a `Sampler` changes the pitch and length of its file.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum StretchEngine {
+    /// Changes the playback speed, so that pitch and length change together.
+    Resample,
+    /// Overlap-adds the best matching frames. Suited to speech and percussive sounds.
+    Wsola,
+    /// Keeps the phases of each frequency coherent. Suited to tonal sounds.
+    PhaseVocoder,
+}
+
+/// Playback settings of a `Sampler`, or of a single note overriding its source.
+/// Fields left as `None` fall back to the source's setting.
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct ScorePlayback {
+    mode: Option<PlaybackMode>,
+    crossfade: Option<f32>,
+    reverse: Option<bool>,
+    start: Option<usize>,
+    end: Option<usize>,
+}
+
+impl ScorePlayback {
+    pub fn new(mode: Option<PlaybackMode>, crossfade: Option<f32>, reverse: Option<bool>,
+        start: Option<usize>, end: Option<usize>) -> Self {
+        Self {
+            mode,
+            crossfade,
+            reverse,
+            start,
+            end,
+        }
+    }
+
+    /// Without a mode, the sustain loop is repeated if there is one and the sample is cut or tiled otherwise.
+    pub fn mode(&self) -> Option<PlaybackMode> {
+        self.mode
+    }
+
+    /// Seconds of the crossfade at the seam of `Loop`.
+    pub fn crossfade(&self) -> Option<f32> {
+        self.crossfade
+    }
+
+    pub fn reverse(&self) -> Option<bool> {
+        self.reverse
+    }
+
+    /// First frame of the file to play.
+    pub fn start(&self) -> Option<usize> {
+        self.start
+    }
+
+    /// Frame just after the last one to play.
+    pub fn end(&self) -> Option<usize> {
+        self.end
+    }
+
+    /// These settings, with the ones left as `None` taken from `other`.
+    pub fn or(self, other: ScorePlayback) -> Self {
+        Self {
+            mode: self.mode.or(other.mode),
+            crossfade: self.crossfade.or(other.crossfade),
+            reverse: self.reverse.or(other.reverse),
+            start: self.start.or(other.start),
+            end: self.end.or(other.end),
+        }
+    }
+}
+
+/// A WAV file played by a `Sampler` source.
+/// Fields left as `None` are read from the file's `smpl` chunk.
+/// A bare path, as written by older scores, is also accepted.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(from = "ScoreSamplerFormat")]
+pub struct ScoreSampler {
+    path: PathBuf,
+    root_note: Option<i32>,
+    fine_tune: Option<f32>,
+    loop_start: Option<usize>,
+    loop_end: Option<usize>,
+    playback: Option<ScorePlayback>,
+    engine: Option<StretchEngine>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScoreSamplerFormat {
+    Path(PathBuf),
+    Parameters {
+        path: PathBuf,
+        root_note: Option<i32>,
+        fine_tune: Option<f32>,
+        loop_start: Option<usize>,
+        loop_end: Option<usize>,
+        playback: Option<ScorePlayback>,
+        engine: Option<StretchEngine>,
+    },
+}
+
+impl From<ScoreSamplerFormat> for ScoreSampler {
+    fn from(format: ScoreSamplerFormat) -> Self {
+        match format {
+            ScoreSamplerFormat::Path(path) => Self::new(path, None, None, None, None, None, None),
+            ScoreSamplerFormat::Parameters { path, root_note, fine_tune, loop_start, loop_end, playback, engine } => {
+                Self::new(path, root_note, fine_tune, loop_start, loop_end, playback, engine)
+            },
+        }
+    }
+}
+
+impl ScoreSampler {
+    pub fn new(path: PathBuf, root_note: Option<i32>, fine_tune: Option<f32>,
+        loop_start: Option<usize>, loop_end: Option<usize>, playback: Option<ScorePlayback>,
+        engine: Option<StretchEngine>) -> Self {
+        Self {
+            path,
+            root_note,
+            fine_tune,
+            loop_start,
+            loop_end,
+            playback,
+            engine,
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Semitone at which the file plays at its recorded pitch.
+    pub fn root_note(&self) -> Option<i32> {
+        self.root_note
+    }
+
+    /// Cents by which the file is sharper than `root_note`.
+    pub fn fine_tune(&self) -> Option<f32> {
+        self.fine_tune
+    }
+
+    /// First frame of the sustain loop.
+    pub fn loop_start(&self) -> Option<usize> {
+        self.loop_start
+    }
+
+    /// Frame just after the end of the sustain loop.
+    pub fn loop_end(&self) -> Option<usize> {
+        self.loop_end
+    }
+
+    pub fn playback(&self) -> Option<ScorePlayback> {
+        self.playback
+    }
+
+    /// Engine used to transpose and stretch the file. `Resample` when `None`.
+    pub fn engine(&self) -> Option<StretchEngine> {
+        self.engine
+    }
+}
+
+/// A sample played by a `MultiSampler` source over a range of keys and velocities.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreSampleZone {
+    sampler: ScoreSampler,
+    keys: (i32, i32),
+    velocities: Option<(f32, f32)>,
+}
+
+impl ScoreSampleZone {
+    pub fn new(sampler: ScoreSampler, keys: (i32, i32), velocities: Option<(f32, f32)>) -> Self {
+        Self {
+            sampler,
+            keys,
+            velocities,
+        }
+    }
+
+    pub fn sampler(&self) -> &ScoreSampler {
+        &self.sampler
+    }
+
+    /// Lowest and highest semitone of the zone.
+    pub fn keys(&self) -> (i32, i32) {
+        self.keys
+    }
+
+    /// Lowest and highest velocity of the zone, or every velocity if `None`.
+    pub fn velocities(&self) -> Option<(f32, f32)> {
+        self.velocities
+    }
+}
+
+/// Zones of a `MultiSampler` source, either inline or in a JSON instrument file.
+/// Relative sample paths in an instrument file are resolved from the file's directory.
+#[derive(Clone, Deserialize, Serialize)]
+pub enum MultiSamplerZones {
+    File(PathBuf),
+    Zones(Vec<ScoreSampleZone>),
+}
+
+/// A WAV file cut into slices played by a `SlicedSampler` source, one per semitone from `first_note`.
+/// Without `slices`, the file is cut at the transients found with `sensitivity`.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreSlicedSampler {
+    path: PathBuf,
+    slices: Option<Vec<usize>>,
+    sensitivity: Option<f32>,
+    first_note: Option<i32>,
+}
+
+impl ScoreSlicedSampler {
+    pub fn new(path: PathBuf, slices: Option<Vec<usize>>, sensitivity: Option<f32>, first_note: Option<i32>) -> Self {
+        Self {
+            path,
+            slices,
+            sensitivity,
+            first_note,
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// First frame of each slice.
+    pub fn slices(&self) -> &Option<Vec<usize>> {
+        &self.slices
+    }
+
+    /// From 0.0 to 1.0. Higher values find softer transients.
+    pub fn sensitivity(&self) -> Option<f32> {
+        self.sensitivity
+    }
+
+    /// Semitone playing the first slice.
+    pub fn first_note(&self) -> Option<i32> {
+        self.first_note
+    }
+}
+
+/// A WAV file played by a `Granular` source as overlapping windowed grains.
+/// Fields left as `None` use the source's defaults. Grains play at the file's recorded
+/// pitch at the root note of its `smpl` chunk, or at semitone 0 without one.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreGranular {
+    path: PathBuf,
+    grain_size: Option<f32>,
+    density: Option<f32>,
+    position: Option<f32>,
+    position_jitter: Option<f32>,
+    pitch_jitter: Option<f32>,
+    seed: Option<u64>,
+}
+
+impl ScoreGranular {
+    pub fn new(path: PathBuf, grain_size: Option<f32>, density: Option<f32>, position: Option<f32>,
+        position_jitter: Option<f32>, pitch_jitter: Option<f32>, seed: Option<u64>) -> Self {
+        Self {
+            path,
+            grain_size,
+            density,
+            position,
+            position_jitter,
+            pitch_jitter,
+            seed,
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Seconds of each grain.
+    pub fn grain_size(&self) -> Option<f32> {
+        self.grain_size
+    }
+
+    /// Grains started per second.
+    pub fn density(&self) -> Option<f32> {
+        self.density
+    }
+
+    /// Where grains are read from, from 0.0 at the start of the file to 1.0 at its end.
+    pub fn position(&self) -> Option<f32> {
+        self.position
+    }
+
+    /// Largest random offset of `position`, as a fraction of the file.
+    pub fn position_jitter(&self) -> Option<f32> {
+        self.position_jitter
+    }
+
+    /// Largest random detune of each grain in semitones.
+    pub fn pitch_jitter(&self) -> Option<f32> {
+        self.pitch_jitter
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+}
+
+/// Parameters of a `KarpusStrong` source.
+/// A bare seed (or `null`), as written by older scores, is also accepted.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(from = "ScoreKarpusStrongFormat")]
+pub struct ScoreKarpusStrong {
+    seed: Option<u64>,
+    decay: Option<f32>,
+    brightness: Option<f32>,
+    stretch: Option<f32>,
+    pick_position: Option<f32>,
+    drum: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScoreKarpusStrongFormat {
+    Seed(Option<u64>),
+    Parameters {
+        seed: Option<u64>,
+        decay: Option<f32>,
+        brightness: Option<f32>,
+        stretch: Option<f32>,
+        pick_position: Option<f32>,
+        drum: Option<f32>,
+    },
+}
+
+impl From<ScoreKarpusStrongFormat> for ScoreKarpusStrong {
+    fn from(format: ScoreKarpusStrongFormat) -> Self {
+        match format {
+            ScoreKarpusStrongFormat::Seed(seed) => Self {
+                seed,
+                ..Default::default()
+            },
+            ScoreKarpusStrongFormat::Parameters { seed, decay, brightness, stretch, pick_position, drum } => {
+                Self::new(seed, decay, brightness, stretch, pick_position, drum)
+            },
+        }
+    }
+}
+
+impl ScoreKarpusStrong {
+    pub fn new(seed: Option<u64>, decay: Option<f32>, brightness: Option<f32>,
+        stretch: Option<f32>, pick_position: Option<f32>, drum: Option<f32>) -> Self {
+        Self {
+            seed,
+            decay,
+            brightness,
+            stretch,
+            pick_position,
+            drum,
+        }
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Seconds for the loop loss alone to decay a note by 60 dB; the loop filter shortens high notes further.
+    /// `None` keeps only the loss of the loop filter.
+    pub fn decay(&self) -> Option<f32> {
+        self.decay
+    }
+
+    /// Low-pass amount of the excitation, from 0.0 (dark) to 1.0 (white noise).
+    pub fn brightness(&self) -> Option<f32> {
+        self.brightness
+    }
+
+    /// Weight of the previous sample in the loop filter. Lower values decay more slowly.
+    pub fn stretch(&self) -> Option<f32> {
+        self.stretch
+    }
+
+    /// Pluck position as a fraction of the string length.
+    pub fn pick_position(&self) -> Option<f32> {
+        self.pick_position
+    }
+
+    /// Probability of inverting each loop sample, 0.0 for a string and around 0.5 for a drum.
+    pub fn drum(&self) -> Option<f32> {
+        self.drum
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum LfoShape {
+    Sine,
+    Triangle,
+    Square,
+    Saw,
+    /// Holds a random value through each cycle.
+    SampleAndHold,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreLfo {
+    shape: LfoShape,
+    rate: f32,
+    delay: Option<f32>,
+    fade_in: Option<f32>,
+}
+
+impl ScoreLfo {
+    pub fn new(shape: LfoShape, rate: f32) -> Self {
+        Self {
+            shape,
+            rate,
+            delay: None,
+            fade_in: None,
+        }
+    }
+
+    pub fn with_delay(self, delay: Option<f32>) -> Self {
+        Self {
+            delay,
+            ..self
+        }
+    }
+
+    pub fn with_fade_in(self, fade_in: Option<f32>) -> Self {
+        Self {
+            fade_in,
+            ..self
+        }
+    }
+
+    pub fn shape(&self) -> LfoShape {
+        self.shape
+    }
+
+    /// Rate in Hz.
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    /// Seconds after note-on before the LFO starts.
+    pub fn delay(&self) -> Option<f32> {
+        self.delay
+    }
+
+    /// Seconds for the LFO to reach its full depth after its delay.
+    pub fn fade_in(&self) -> Option<f32> {
+        self.fade_in
+    }
+}
+
+/// Parameter of a note moved by an LFO.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum LfoTarget {
+    /// Semitones added to the pitch.
+    Pitch,
+    /// Added to a gain of 1.0.
+    Amplitude,
+    /// Added to the position from -1.0 (left) to 1.0 (right).
+    Pan,
+    /// Octaves added to the filter cutoff of `Subtractive` sources and of the note's modulation.
+    Cutoff,
+    /// Added to the position of `Wavetable` and `Granular` sources.
+    Position,
+    /// Added to the width of `Pulse` sources.
+    Width,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreLfoRoute {
+    lfo: ScoreLfo,
+    target: LfoTarget,
+    depth: f32,
+    sync: Option<f32>,
+}
+
+impl ScoreLfoRoute {
+    pub fn new(lfo: ScoreLfo, target: LfoTarget, depth: f32) -> Self {
+        Self {
+            lfo,
+            target,
+            depth,
+            sync: None,
+        }
+    }
+
+    pub fn with_sync(self, sync: Option<f32>) -> Self {
+        Self {
+            sync,
+            ..self
+        }
+    }
+
+    pub fn lfo(&self) -> &ScoreLfo {
+        &self.lfo
+    }
+
+    pub fn target(&self) -> LfoTarget {
+        self.target
+    }
+
+    /// Largest offset of the target, in its own unit.
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    /// Beats per cycle of the LFO, replacing its rate.
+    pub fn sync(&self) -> Option<f32> {
+        self.sync
+    }
+
+    /// The routed LFO, synced to `bpm` if it has a sync.
+    pub fn to_lfo_route(&self, bpm: f32) -> LfoRoute {
+        let lfo = Lfo::from(&self.lfo);
+        let lfo = match self.sync {
+            Some(beats) if beats > 0f32 => lfo.with_rate(bpm / 60f32 / beats),
+            _ => lfo,
+        };
+
+        LfoRoute::new(lfo, self.target, self.depth)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum OscillatorWaveform {
+    Sin,
+    Triangle,
+    Saw,
+    Square,
+    Pulse {
+        width: f32,
+    },
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreOscillator {
+    waveform: OscillatorWaveform,
+    level: Option<f32>,
+    semitone: Option<f32>,
+}
+
+impl ScoreOscillator {
+    pub fn new(waveform: OscillatorWaveform, level: Option<f32>, semitone: Option<f32>) -> Self {
+        Self {
+            waveform,
+            level,
+            semitone,
+        }
+    }
+
+    pub fn waveform(&self) -> OscillatorWaveform {
+        self.waveform
+    }
+
+    pub fn level(&self) -> Option<f32> {
+        self.level
+    }
+
+    /// Offset from the note in semitones.
+    pub fn semitone(&self) -> Option<f32> {
+        self.semitone
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum FilterMode {
+    LowPass,
+    BandPass,
+    HighPass,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreFilter {
+    mode: FilterMode,
+    cutoff: f32,
+    resonance: f32,
+    key_tracking: Option<f32>,
+    envelope: Option<ScoreEnvelope>,
+    envelope_depth: Option<f32>,
+}
+
+impl ScoreFilter {
+    pub fn new(mode: FilterMode, cutoff: f32, resonance: f32, key_tracking: Option<f32>,
+        envelope: Option<ScoreEnvelope>, envelope_depth: Option<f32>) -> Self {
+        Self {
+            mode,
+            cutoff,
+            resonance,
+            key_tracking,
+            envelope,
+            envelope_depth,
+        }
+    }
+
+    pub fn mode(&self) -> FilterMode {
+        self.mode
+    }
+
+    /// Cutoff in Hz at semitone 0.
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff
+    }
+
+    pub fn resonance(&self) -> f32 {
+        self.resonance
+    }
+
+    /// How much the cutoff follows the note, 1.0 moving it by the same interval.
+    pub fn key_tracking(&self) -> Option<f32> {
+        self.key_tracking
+    }
+
+    pub fn envelope(&self) -> &Option<ScoreEnvelope> {
+        &self.envelope
+    }
+
+    /// Cutoff shift in octaves at full envelope level.
+    pub fn envelope_depth(&self) -> Option<f32> {
+        self.envelope_depth
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreSubtractive {
+    oscillators: Vec<ScoreOscillator>,
+    filter: ScoreFilter,
+    lfo: Option<ScoreLfo>,
+    lfo_pitch: Option<f32>,
+    lfo_cutoff: Option<f32>,
+}
+
+impl ScoreSubtractive {
+    pub fn new(oscillators: Vec<ScoreOscillator>, filter: ScoreFilter,
+        lfo: Option<ScoreLfo>, lfo_pitch: Option<f32>, lfo_cutoff: Option<f32>) -> Self {
+        Self {
+            oscillators,
+            filter,
+            lfo,
+            lfo_pitch,
+            lfo_cutoff,
+        }
+    }
+
+    pub fn oscillators(&self) -> &Vec<ScoreOscillator> {
+        &self.oscillators
+    }
+
+    pub fn filter(&self) -> &ScoreFilter {
+        &self.filter
+    }
+
+    pub fn lfo(&self) -> &Option<ScoreLfo> {
+        &self.lfo
+    }
+
+    /// Vibrato depth in semitones.
+    pub fn lfo_pitch(&self) -> Option<f32> {
+        self.lfo_pitch
+    }
+
+    /// Cutoff modulation depth in octaves.
+    pub fn lfo_cutoff(&self) -> Option<f32> {
+        self.lfo_cutoff
+    }
+}
+
+/// A resonant mode of a `Modal` source.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreMode {
+    ratio: f32,
+    amplitude: f32,
+    decay: f32,
+}
+
+impl ScoreMode {
+    pub fn new(ratio: f32, amplitude: f32, decay: f32) -> Self {
+        Self {
+            ratio,
+            amplitude,
+            decay,
+        }
+    }
+
+    /// Frequency relative to the note.
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+
+    pub fn amplitude(&self) -> f32 {
+        self.amplitude
+    }
+
+    /// Time in seconds for the mode to decay to 1/e.
+    pub fn decay(&self) -> f32 {
+        self.decay
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub enum ModalPreset {
+    Marimba,
+    Vibraphone,
+    Bell,
+    Plate,
+    Custom(Vec<ScoreMode>),
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum ModalExcitation {
+    Impulse,
+    Noise {
+        length: f32,
+    },
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub enum ScorePartSource {
-    Sampler(PathBuf),
+    Sampler(ScoreSampler),
+    MultiSampler(MultiSamplerZones),
+    SoundFont {
+        path: PathBuf,
+        bank: u16,
+        preset: u16,
+    },
+    Sfz(PathBuf),
     Sin,
     Triangle,
-    KarpusStrong(Option<u64>),
+    KarpusStrong(ScoreKarpusStrong),
+    Square,
+    Saw,
+    Pulse {
+        width: f32,
+    },
+    Wavetable {
+        frames: WavetableFrames,
+        position: f32,
+    },
+    Fm {
+        operators: Vec<ScoreFmOperator>,
+        algorithm: FmAlgorithm,
+    },
+    Additive(Vec<ScorePartial>),
+    Noise {
+        color: NoiseColor,
+        seed: Option<u64>,
+        resonance: Option<f32>,
+    },
+    DrumSynth {
+        voices: Option<Vec<ScoreDrumVoice>>,
+        seed: Option<u64>,
+    },
+    Subtractive(ScoreSubtractive),
+    Modal {
+        preset: ModalPreset,
+        excitation: ModalExcitation,
+        seed: Option<u64>,
+    },
+    Granular(ScoreGranular),
+    SlicedSampler(ScoreSlicedSampler),
 }
 
 impl From<ScorePartSource> for Result<Box<dyn SoundSource>, Box<dyn Error>> {
     fn from(score_part_source: ScorePartSource) -> Self {
         match score_part_source {
-            ScorePartSource::Sampler(src_file) => {
-                let sound_source = Sampler::new(src_file)?;
+            ScorePartSource::Sampler(sampler) => {
+                let sound_source = Sampler::new(sampler)?;
                 Ok(Box::new(sound_source))
             },
+            ScorePartSource::MultiSampler(zones) => {
+                Ok(Box::new(MultiSampler::new(zones)?))
+            },
+            ScorePartSource::SoundFont { path, bank, preset } => {
+                Ok(Box::new(SoundFont::new(path, bank, preset)?))
+            },
+            ScorePartSource::Sfz(path) => {
+                Ok(Box::new(Sfz::new(path)?))
+            },
             ScorePartSource::Sin => {
                 Ok(Box::new(Sin))
             },
             ScorePartSource::Triangle => {
                 Ok(Box::new(Triangle))
             },
-            ScorePartSource::KarpusStrong(seed) => {
-                Ok(Box::new(KarpusStrong::new(seed)))
+            ScorePartSource::KarpusStrong(karpus_strong) => {
+                Ok(Box::new(KarpusStrong::new(karpus_strong)))
+            },
+            ScorePartSource::Square => {
+                Ok(Box::new(Square))
+            },
+            ScorePartSource::Saw => {
+                Ok(Box::new(Saw))
+            },
+            ScorePartSource::Pulse { width } => {
+                Ok(Box::new(Pulse::new(width)))
+            },
+            ScorePartSource::Wavetable { frames, position } => {
+                Ok(Box::new(Wavetable::new(frames, position)?))
+            },
+            ScorePartSource::Fm { operators, algorithm } => {
+                Ok(Box::new(Fm::new(operators, algorithm)?))
+            },
+            ScorePartSource::Additive(partials) => {
+                Ok(Box::new(Additive::new(partials)))
+            },
+            ScorePartSource::Noise { color, seed, resonance } => {
+                Ok(Box::new(Noise::new(color, seed, resonance)))
+            },
+            ScorePartSource::DrumSynth { voices, seed } => {
+                Ok(Box::new(DrumSynth::new(voices, seed)))
+            },
+            ScorePartSource::Subtractive(subtractive) => {
+                Ok(Box::new(Subtractive::new(subtractive)?))
+            },
+            ScorePartSource::Modal { preset, excitation, seed } => {
+                Ok(Box::new(Modal::new(preset, excitation, seed)))
+            },
+            ScorePartSource::Granular(granular) => {
+                Ok(Box::new(Granular::new(granular)?))
+            },
+            ScorePartSource::SlicedSampler(sliced_sampler) => {
+                Ok(Box::new(SlicedSampler::new(sliced_sampler)?))
             },
         }
     }
 }
 
-#[derive(Deserialize, Serialize)]
+/// Shape of an envelope segment, as the part of its change done over its time.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum EnvelopeCurve {
+    Linear,
+    /// Changes fast at first and then slows down, like a natural decay.
+    Exponential,
+    /// Changes slowly at first and then speeds up.
+    Logarithmic,
+    /// Follows the elapsed part of the segment raised to this power.
+    Power(f32),
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct ScoreEnvelope {
     attack: f32,
     decay: f32,
     sustain: f32,
     release: f32,
+    delay: Option<f32>,
+    hold: Option<f32>,
+    attack_curve: Option<EnvelopeCurve>,
+    decay_curve: Option<EnvelopeCurve>,
+    release_curve: Option<EnvelopeCurve>,
 }
 
 impl ScoreEnvelope {
@@ -57,6 +1053,35 @@ impl ScoreEnvelope {
             decay,
             sustain,
             release,
+            delay: None,
+            hold: None,
+            attack_curve: None,
+            decay_curve: None,
+            release_curve: None,
+        }
+    }
+
+    pub fn with_delay(self, delay: Option<f32>) -> Self {
+        Self {
+            delay,
+            ..self
+        }
+    }
+
+    pub fn with_hold(self, hold: Option<f32>) -> Self {
+        Self {
+            hold,
+            ..self
+        }
+    }
+
+    pub fn with_curves(self, attack_curve: Option<EnvelopeCurve>, decay_curve: Option<EnvelopeCurve>,
+        release_curve: Option<EnvelopeCurve>) -> Self {
+        Self {
+            attack_curve,
+            decay_curve,
+            release_curve,
+            ..self
         }
     }
 
@@ -75,6 +1100,28 @@ impl ScoreEnvelope {
     pub fn release(&self) -> f32 {
         self.release
     }
+
+    /// Seconds of silence before the attack.
+    pub fn delay(&self) -> Option<f32> {
+        self.delay
+    }
+
+    /// Seconds held at full level between the attack and the decay.
+    pub fn hold(&self) -> Option<f32> {
+        self.hold
+    }
+
+    pub fn attack_curve(&self) -> Option<EnvelopeCurve> {
+        self.attack_curve
+    }
+
+    pub fn decay_curve(&self) -> Option<EnvelopeCurve> {
+        self.decay_curve
+    }
+
+    pub fn release_curve(&self) -> Option<EnvelopeCurve> {
+        self.release_curve
+    }
 }
 
 
@@ -86,6 +1133,275 @@ impl From<ScoreEnvelope> for Envelope {
             score_envelope.sustain,
             score_envelope.release,
         )
+            .with_delay(score_envelope.delay.unwrap_or(0f32))
+            .with_hold(score_envelope.hold.unwrap_or(0f32))
+            .with_curves(
+                score_envelope.attack_curve.unwrap_or(EnvelopeCurve::Linear),
+                score_envelope.decay_curve.unwrap_or(EnvelopeCurve::Linear),
+                score_envelope.release_curve.unwrap_or(EnvelopeCurve::Linear),
+            )
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreBreakpoint {
+    time: f32,
+    level: f32,
+    curve: Option<EnvelopeCurve>,
+}
+
+impl ScoreBreakpoint {
+    pub fn new(time: f32, level: f32, curve: Option<EnvelopeCurve>) -> Self {
+        Self {
+            time,
+            level,
+            curve,
+        }
+    }
+
+    /// Seconds after note-on.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Shape of the segment reaching this point. `Linear` when `None`.
+    pub fn curve(&self) -> Option<EnvelopeCurve> {
+        self.curve
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreBreakpointEnvelope {
+    points: Vec<ScoreBreakpoint>,
+    sustain: Option<usize>,
+    loop_start: Option<usize>,
+}
+
+impl ScoreBreakpointEnvelope {
+    pub fn new(points: Vec<ScoreBreakpoint>, sustain: Option<usize>, loop_start: Option<usize>) -> Self {
+        Self {
+            points,
+            sustain,
+            loop_start,
+        }
+    }
+
+    pub fn points(&self) -> &Vec<ScoreBreakpoint> {
+        &self.points
+    }
+
+    /// Index of the point held until note-off.
+    pub fn sustain(&self) -> Option<usize> {
+        self.sustain
+    }
+
+    /// Index of the point the envelope goes back to from the sustain point until note-off.
+    pub fn loop_start(&self) -> Option<usize> {
+        self.loop_start
+    }
+}
+
+impl From<ScoreBreakpointEnvelope> for BreakpointEnvelope {
+    fn from(score_envelope: ScoreBreakpointEnvelope) -> Self {
+        Self::new(
+            score_envelope.points.into_iter()
+                .map(|point| Breakpoint::new(point.time, point.level, point.curve.unwrap_or(EnvelopeCurve::Linear)))
+                .collect(),
+            score_envelope.sustain,
+            score_envelope.loop_start,
+        )
+    }
+}
+
+/// Breakpoint envelopes modulating a note.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct ScoreModulation {
+    amplitude: Option<ScoreBreakpointEnvelope>,
+    pitch: Option<ScoreBreakpointEnvelope>,
+    cutoff: Option<ScoreBreakpointEnvelope>,
+    pan: Option<ScoreBreakpointEnvelope>,
+    lfos: Option<Vec<ScoreLfoRoute>>,
+}
+
+impl ScoreModulation {
+    pub fn new(amplitude: Option<ScoreBreakpointEnvelope>, pitch: Option<ScoreBreakpointEnvelope>,
+        cutoff: Option<ScoreBreakpointEnvelope>, pan: Option<ScoreBreakpointEnvelope>) -> Self {
+        Self {
+            amplitude,
+            pitch,
+            cutoff,
+            pan,
+            lfos: None,
+        }
+    }
+
+    pub fn with_lfos(self, lfos: Option<Vec<ScoreLfoRoute>>) -> Self {
+        Self {
+            lfos,
+            ..self
+        }
+    }
+
+    /// Gain multiplying the note.
+    pub fn amplitude(&self) -> &Option<ScoreBreakpointEnvelope> {
+        &self.amplitude
+    }
+
+    /// Semitones added to the note.
+    pub fn pitch(&self) -> &Option<ScoreBreakpointEnvelope> {
+        &self.pitch
+    }
+
+    /// Cutoff of a low-pass filter in Hz.
+    pub fn cutoff(&self) -> &Option<ScoreBreakpointEnvelope> {
+        &self.cutoff
+    }
+
+    /// Position from -1.0 (left) to 1.0 (right).
+    pub fn pan(&self) -> &Option<ScoreBreakpointEnvelope> {
+        &self.pan
+    }
+
+    /// LFOs replacing those of the part for this note.
+    pub fn lfos(&self) -> &Option<Vec<ScoreLfoRoute>> {
+        &self.lfos
+    }
+}
+
+/// The envelopes only. LFOs need the tempo of the part and are added to the modulation by it.
+impl From<ScoreModulation> for Modulation {
+    fn from(score_modulation: ScoreModulation) -> Self {
+        Self::new(
+            score_modulation.amplitude.map(|envelope| envelope.into()),
+            score_modulation.pitch.map(|envelope| envelope.into()),
+            score_modulation.cutoff.map(|envelope| envelope.into()),
+            score_modulation.pan.map(|envelope| envelope.into()),
+        )
+    }
+}
+
+/// Pitch of a note bending from an offset at its start, and to an offset at its end.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub struct ScoreBend {
+    from: Option<f32>,
+    to: Option<f32>,
+    time: Option<f32>,
+    curve: Option<EnvelopeCurve>,
+}
+
+impl ScoreBend {
+    pub fn new(from: Option<f32>, to: Option<f32>, time: Option<f32>, curve: Option<EnvelopeCurve>) -> Self {
+        Self {
+            from,
+            to,
+            time,
+            curve,
+        }
+    }
+
+    /// Semitones from the note at note-on, bending back to the note.
+    pub fn from(&self) -> Option<f32> {
+        self.from
+    }
+
+    /// Semitones from the note reached at note-off.
+    pub fn to(&self) -> Option<f32> {
+        self.to
+    }
+
+    /// Seconds each bend takes. When `None`, the whole note, or half of it when the note
+    /// both slides in and bends to `to`.
+    pub fn time(&self) -> Option<f32> {
+        self.time
+    }
+
+    /// Shape of the bends in semitones. `Linear` when `None`.
+    pub fn curve(&self) -> Option<EnvelopeCurve> {
+        self.curve
+    }
+}
+
+/// Notes of a part sliding from the pitch of the previous note.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub struct ScoreGlide {
+    time: f32,
+    legato: Option<bool>,
+}
+
+impl ScoreGlide {
+    pub fn new(time: f32, legato: Option<bool>) -> Self {
+        Self {
+            time,
+            legato,
+        }
+    }
+
+    /// Seconds to reach the pitch of the note.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Only notes starting before the previous one ends slide, continuing its sound
+    /// without starting a new one. Otherwise every note slides from the previous one.
+    pub fn legato(&self) -> bool {
+        self.legato.unwrap_or(false)
+    }
+}
+
+/// Appends the pitch of a note to the points of its voice: `level` semitones from the voice's note
+/// for `length` seconds from `offset`, sliding from `glide_from` over `glide_time` seconds
+/// unless the note bends from elsewhere. Returns the level reached at the end of the note.
+fn push_pitch_points(points: &mut Vec<Breakpoint>, offset: f32, length: f32, level: f32,
+    glide_from: Option<f32>, glide_time: f32, bend: Option<&ScoreBend>) -> f32 {
+    // A note starting before the end of the previous one cuts its points short.
+    points.retain(|point| point.time() <= offset);
+    let mut push = |time: f32, level: f32, curve: EnvelopeCurve| {
+        let time = points.last().map_or(time, |last| time.max(last.time()));
+        points.push(Breakpoint::new(time, level, curve));
+    };
+    let curve = bend.and_then(|bend| bend.curve).unwrap_or(EnvelopeCurve::Linear);
+    let from = bend.and_then(|bend| bend.from);
+    let to = bend.and_then(|bend| bend.to);
+
+    // The slide into the note, from its bend or its glide, and the bend out of it share the note.
+    let is_sliding_in = from.is_some() || glide_from.is_some();
+    let bend_time = bend.and_then(|bend| bend.time).unwrap_or(match is_sliding_in && to.is_some() {
+        true => length / 2f32,
+        false => length,
+    }).max(0f32);
+    let in_time = match from {
+        Some(_) => bend_time,
+        None => glide_time.max(0f32),
+    };
+    let out_time = if to.is_some() { bend_time } else { 0f32 };
+    let scale = match in_time + out_time {
+        total if total > length => length / total,
+        _ => 1f32,
+    };
+
+    match (from, glide_from) {
+        (Some(from), _) => {
+            push(offset, level + from, EnvelopeCurve::Linear);
+            push(offset + in_time * scale, level, curve);
+        },
+        (None, Some(glide_from)) => {
+            push(offset, glide_from, EnvelopeCurve::Linear);
+            push(offset + in_time * scale, level, EnvelopeCurve::Linear);
+        },
+        (None, None) => push(offset, level, EnvelopeCurve::Linear),
+    }
+
+    match to {
+        Some(to) => {
+            push(offset + length - out_time * scale, level, EnvelopeCurve::Linear);
+            push(offset + length, level + to, curve);
+            level + to
+        },
+        None => level,
     }
 }
 
@@ -95,15 +1411,31 @@ pub struct ScoreNote {
     start: Option<f32>,
     length: f32,
     envelope: Option<ScoreEnvelope>,
+    velocity: Option<f32>,
+    playback: Option<ScorePlayback>,
+    modulation: Option<ScoreModulation>,
+    bend: Option<ScoreBend>,
 }
 
 impl ScoreNote {
-    pub fn new(semitone: Option<f32>, start: Option<f32>, length: f32, envelope: Option<ScoreEnvelope>) -> Self {
+    pub fn new(semitone: Option<f32>, start: Option<f32>, length: f32, envelope: Option<ScoreEnvelope>,
+        velocity: Option<f32>, playback: Option<ScorePlayback>, modulation: Option<ScoreModulation>) -> Self {
         Self {
             semitone,
             start,
             length,
             envelope,
+            velocity,
+            playback,
+            modulation,
+            bend: None,
+        }
+    }
+
+    pub fn with_bend(self, bend: Option<ScoreBend>) -> Self {
+        Self {
+            bend,
+            ..self
         }
     }
 
@@ -111,6 +1443,8 @@ impl ScoreNote {
         self.semitone
     }
 
+    /// Beats from the start of the part. Notes without one start at the note-off of the previous ones,
+    /// so that the release tails of those overlap them.
     pub fn start(&self) -> Option<f32> {
         self.start
     }
@@ -122,6 +1456,32 @@ impl ScoreNote {
     pub fn envelope(&self) -> &Option<ScoreEnvelope> {
         &self.envelope
     }
+
+    /// Strength of the note from 0.0 to 1.0.
+    pub fn velocity(&self) -> Option<f32> {
+        self.velocity
+    }
+
+    /// Overrides the playback of a `Sampler` source for this note.
+    pub fn playback(&self) -> Option<ScorePlayback> {
+        self.playback
+    }
+
+    pub fn modulation(&self) -> &Option<ScoreModulation> {
+        &self.modulation
+    }
+
+    pub fn bend(&self) -> Option<ScoreBend> {
+        self.bend
+    }
+}
+
+/// How a multi-channel track is mixed into one channel.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum Downmix {
+    Mix,
+    Left,
+    Right,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -132,13 +1492,17 @@ pub struct ScorePart {
     volume: Option<f32>,
     channel: Option<u16>,
     envelope: Option<ScoreEnvelope>,
+    downmix: Option<Downmix>,
+    lfos: Option<Vec<ScoreLfoRoute>>,
+    glide: Option<ScoreGlide>,
 }
 
 impl ScorePart {
     const DEFAULT_CHANNEL: u16 = 0u16;
 
     pub fn new(source: ScorePartSource, bpm: f32, score_notes: Vec<ScoreNote>,
-        volume: Option<f32>, channel: Option<u16>, envelope: Option<ScoreEnvelope>) -> Self {
+        volume: Option<f32>, channel: Option<u16>, envelope: Option<ScoreEnvelope>,
+        downmix: Option<Downmix>) -> Self {
         Self {
             source,
             bpm,
@@ -146,6 +1510,23 @@ impl ScorePart {
             volume,
             channel,
             envelope,
+            downmix,
+            lfos: None,
+            glide: None,
+        }
+    }
+
+    pub fn with_lfos(self, lfos: Option<Vec<ScoreLfoRoute>>) -> Self {
+        Self {
+            lfos,
+            ..self
+        }
+    }
+
+    pub fn with_glide(self, glide: Option<ScoreGlide>) -> Self {
+        Self {
+            glide,
+            ..self
         }
     }
 
@@ -171,30 +1552,136 @@ impl ScorePart {
             None => Self::DEFAULT_CHANNEL,
         }
     }
-}
 
-impl From<ScorePart> for Result<InstrumentTrack, Box<dyn Error>> {
-    fn from(score_part: ScorePart) -> Self {
-        let source: Result<Box<dyn SoundSource>, Box<dyn Error>> = score_part.source.into();
+    /// Policy to mix multi-channel sources into the track's channel.
+    /// Without one, they fill the following channels too when the mixdown has them.
+    pub fn downmix(&self) -> Option<Downmix> {
+        self.downmix
+    }
+
+    /// LFOs moving every note, unless a note has its own.
+    pub fn lfos(&self) -> &Option<Vec<ScoreLfoRoute>> {
+        &self.lfos
+    }
+
+    pub fn glide(&self) -> Option<ScoreGlide> {
+        self.glide
+    }
+
+    pub fn into_instrument_track(self, sample_rate: u32) -> Result<InstrumentTrack, Box<dyn Error>> {
+        let source: Result<Box<dyn SoundSource>, Box<dyn Error>> = self.source.into();
         let source = source?;
-        let volume = match score_part.volume {
+        let volume = match self.volume {
             Some(volume) => volume,
             None => DEFAULT_VOLUME,
         };
-        let envelope: Option<Envelope> = match score_part.envelope {
-            Some(envelope) => Some(envelope.into()),
-            None => None,
-        };
+        let envelope: Option<Envelope> = self.envelope.map(|envelope| envelope.into());
+
+        let mut track = InstrumentTrack::new(sample_rate, volume, envelope);
+
+        // Notes without a start follow the note-off of the previous ones, so that release tails overlap them.
+        // Under a legato glide, notes starting before the previous one ends join its voice.
+        let is_legato = self.glide.is_some_and(|glide| glide.legato());
+        let mut voices: Vec<Vec<(f32, f32, ScoreNote)>> = vec![];
+        let mut position = 0f32;
+        for score_note in self.score_notes {
+            let start = score_note.start.map_or(position, |start| 60f32 / self.bpm * start);
+            let length = 60f32 / self.bpm * score_note.length;
+            position = position.max(start + length);
+            match voices.last_mut() {
+                Some(voice) if is_legato && score_note.semitone.is_some() && voice[0].2.semitone.is_some()
+                    && start >= voice[0].0
+                    && voice.iter().any(|(voice_start, voice_length, _)| start <= voice_start + voice_length) => {
+                    voice.push((start, length, score_note));
+                },
+                _ => voices.push(vec![(start, length, score_note)]),
+            }
+        }
 
-        let mut track = InstrumentTrack::new(source.sample_rate(), volume, envelope);
+        // Semitones the last note ended at, which the next one slides from.
+        let mut glide_from: Option<f32> = None;
+        for voice in voices {
+            let length = voice.iter().map(|(start, length, _)| start + length).fold(0f32, f32::max) - voice[0].0;
+            // Voices with glides or bends follow a pitch curve relative to their first note.
+            let bend = match (voice[0].2.semitone, self.glide) {
+                (Some(semitone), glide) if glide.is_some() || voice.iter().any(|(_, _, note)| note.bend.is_some()) => {
+                    let mut points = vec![];
+                    // Only glides slide in from the previous voice.
+                    let mut previous = match is_legato {
+                        false if glide.is_some() => glide_from.map(|glide_from| glide_from - semitone),
+                        _ => None,
+                    };
+                    for (start, length, note) in voice.iter() {
+                        let level = note.semitone.unwrap_or(semitone) - semitone;
+                        let end = push_pitch_points(&mut points, start - voice[0].0, *length, level,
+                            previous, glide.map_or(0f32, |glide| glide.time()), note.bend.as_ref());
+                        previous = Some(end);
+                    }
+                    glide_from = previous.map(|previous| previous + semitone);
+                    Some(BreakpointEnvelope::new(points, None, None))
+                },
+                (Some(semitone), None) => {
+                    glide_from = Some(semitone);
+                    None
+                },
+                _ => None,
+            };
 
-        for score_note in score_part.score_notes {
-            let start = match score_note.start {
-                Some(start) => Some(60f32 / score_part.bpm * start),
-                None => None,
+            let Some((start, _, score_note)) = voice.into_iter().next() else {
+                continue;
+            };
+            let velocity = score_note.velocity.unwrap_or(DEFAULT_VELOCITY);
+            let note_envelope: Option<Envelope> = score_note.envelope.map(|envelope| envelope.into());
+            let lfos: Vec<LfoRoute> = score_note.modulation.as_ref()
+                .and_then(|modulation| modulation.lfos().as_ref())
+                .or(self.lfos.as_ref())
+                .map_or(vec![], |lfos| lfos.iter().map(|lfo| lfo.to_lfo_route(self.bpm)).collect());
+            // Rests have nothing to modulate.
+            let modulation: Option<Modulation> = match score_note.semitone {
+                Some(_) if score_note.modulation.is_some() || !lfos.is_empty() || bend.is_some() => {
+                    let modulation: Modulation = score_note.modulation.unwrap_or_default().into();
+                    Some(modulation.with_lfos(lfos.clone()).with_bend(bend))
+                },
+                _ => None,
+            };
+            // Notes are rendered past note-off for as long as their release may sound.
+            let release = note_envelope.as_ref().or(track.envelope().as_ref())
+                .map_or(0f32, |envelope| envelope.release())
+                .max(source.release())
+                .max(modulation.as_ref().map_or(0f32, |modulation| modulation.release()));
+            let seconds = length + release;
+            let (source_seconds, source_note_off) = match modulation.as_ref() {
+                Some(modulation) => (
+                    modulation.source_seconds(seconds, length, sample_rate),
+                    modulation.source_seconds(length, length, sample_rate),
+                ),
+                None => (seconds, length),
+            };
+            let note = match score_note.semitone {
+                Some(semitone) => source.get_note(&NoteRequest::new(semitone, source_seconds, sample_rate)
+                    .with_note_off(source_note_off)
+                    .with_velocity(velocity)
+                    .with_playback(score_note.playback)
+                    .with_lfos(lfos.clone())),
+                None => source.get_rest_note(source_seconds, sample_rate),
             };
-            let length = 60f32 / score_part.bpm * score_note.length;
-            track.add_note(start, source.get_note(length, score_note.semitone));
+            // One-shot notes are held until their sound ends, and only released after it.
+            let (seconds, note_off) = match source.is_one_shot(score_note.playback) {
+                true => {
+                    let note_off = (length + note.data().len() as f32 / note.sample_rate() as f32 - source_note_off).max(length);
+                    (note_off + release, note_off)
+                },
+                false => (seconds, length),
+            };
+            let note = match modulation {
+                Some(modulation) => modulation.apply(note, seconds, note_off),
+                None => note,
+            };
+            let note = match note_envelope {
+                Some(envelope) => note.with_envelope(Some(envelope)),
+                None => note,
+            };
+            track.add_note(Some(start), note, note_off);
         }
 
         Ok(track)
@@ -235,10 +1722,110 @@ impl From<Score> for Result<Mixdown, Box<dyn Error>> {
         let mut mixdown = Mixdown::new(score.num_channel, score.sample_rate);
         for track in score.tracks {
             let channel = track.channel();
-            let instrument_track: Result<InstrumentTrack, Box<dyn Error>> = track.into();
-            mixdown.add_track(channel, instrument_track?)?;
+            let downmix = track.downmix();
+            let mut instrument_track = track.into_instrument_track(score.sample_rate)?;
+            let fits = (channel as usize + instrument_track.num_channels()) <= score.num_channel as usize;
+            if instrument_track.num_channels() > 1 && (downmix.is_some() || !fits) {
+                instrument_track = instrument_track.downmix(downmix.unwrap_or(Downmix::Mix));
+            }
+            mixdown.add_track(channel, instrument_track)?;
         }
 
         Ok(mixdown)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pitch_curve(length: f32, glide_from: Option<f32>, glide_time: f32, bend: ScoreBend) -> BreakpointEnvelope {
+        let mut points = vec![];
+        push_pitch_points(&mut points, 0f32, length, 0f32, glide_from, glide_time, Some(&bend));
+
+        BreakpointEnvelope::new(points, None, None)
+    }
+
+    #[test]
+    fn bends_from_and_to_share_the_note() {
+        let curve = pitch_curve(1f32, None, 0f32, ScoreBend::new(Some(-2f32), Some(2f32), None, None));
+
+        assert_eq!(curve.value(0f32, 1f32), -2f32);
+        assert!((curve.value(0.25f32, 1f32) + 1f32).abs() < 1e-4);
+        assert_eq!(curve.value(0.5f32, 1f32), 0f32);
+        // Halfway through the bend to `to`.
+        assert!((curve.value(0.75f32, 1f32) - 1f32).abs() < 1e-4);
+        assert_eq!(curve.value(1f32, 1f32), 2f32);
+    }
+
+    #[test]
+    fn long_glide_and_bend_are_shortened_to_fit() {
+        // A glide of 0.8s and a bend of 0.6s are scaled to 4/7 and 3/7 of the note.
+        let curve = pitch_curve(1f32, Some(-12f32), 0.8f32, ScoreBend::new(None, Some(2f32), Some(0.6f32), None));
+
+        assert!((curve.value(2f32 / 7f32, 1f32) + 6f32).abs() < 1e-3);
+        assert!((curve.value(4f32 / 7f32, 1f32)).abs() < 1e-3);
+        assert!((curve.value(11f32 / 14f32, 1f32) - 1f32).abs() < 1e-3);
+        assert_eq!(curve.value(1f32, 1f32), 2f32);
+    }
+
+    #[test]
+    fn one_shot_notes_keep_the_whole_sample() {
+        let path = std::env::temp_dir().join("sound_sampler_one_shot.wav");
+        crate::utils::write_wav(&path, &[vec![0.5f32; 44_100]], 44_100).unwrap();
+        let playback = ScorePlayback::new(Some(PlaybackMode::OneShot), None, None, None, None);
+        let sampler = ScoreSampler::new(path.clone(), Some(0), None, None, None, Some(playback), None);
+        let note = ScoreNote::new(Some(0f32), Some(0f32), 0.25f32, Some(ScoreEnvelope::new(0f32, 0f32, 1f32, 0.1f32)),
+            None, None, None);
+        let part = ScorePart::new(ScorePartSource::Sampler(sampler), 60f32, vec![note], None, None, None, None);
+        let track = part.into_instrument_track(44_100);
+        std::fs::remove_file(&path).unwrap();
+
+        let track = track.unwrap();
+        let data = &track.data()[0];
+        // The release starts at the end of the sample, not at the note-off.
+        assert!(track.length() >= 48_510);
+        assert!((data[39_690] - data[4_410]).abs() < 1e-4);
+        assert!(data[4_410] > 0f32);
+    }
+
+    #[test]
+    fn notes_without_start_follow_the_previous_note_off() {
+        let envelope = ScoreEnvelope::new(0f32, 0f32, 1f32, 0.5f32);
+        let notes = vec![
+            ScoreNote::new(Some(0f32), None, 1f32, Some(envelope.clone()), None, None, None),
+            ScoreNote::new(Some(0f32), None, 1f32, Some(envelope), None, None, None),
+        ];
+        let part = ScorePart::new(ScorePartSource::Sin, 60f32, notes, None, None, None, None);
+        let track = part.into_instrument_track(1_000).unwrap();
+
+        // The second note starts at 1s, during the release of the first, and is released in turn at 2s.
+        assert_eq!(track.length(), 2_500);
+    }
+
+    #[test]
+    fn bends_without_glide_start_at_their_own_pitch() {
+        let notes = vec![
+            ScoreNote::new(Some(-12f32), None, 1f32, None, None, None, None),
+            ScoreNote::new(Some(0f32), None, 1f32, None, None, None, None)
+                .with_bend(Some(ScoreBend::new(None, Some(2f32), None, None))),
+        ];
+        let part = ScorePart::new(ScorePartSource::Sin, 60f32, notes, None, None, None, None);
+        let data = part.into_instrument_track(48_000).unwrap().data()[0].clone();
+
+        // Seconds per cycle of the first full cycle after `from` seconds.
+        let period = |from: f32| {
+            let crossings: Vec<usize> = ((from * 48_000f32) as usize..data.len() - 1)
+                .filter(|&i| data[i] <= 0f32 && data[i + 1] > 0f32)
+                .take(2)
+                .collect();
+            (crossings[1] - crossings[0]) as f32 / 48_000f32
+        };
+        let semitone = |from: f32| crate::source::frequency_to_semitone(1f32 / period(from));
+        // The second note starts at its own pitch, not the first one's, and bends over its whole length.
+        assert!((semitone(0.5f32) + 12f32).abs() < 0.1f32);
+        assert!(semitone(1f32).abs() < 0.1f32);
+        assert!((semitone(1.5f32) - 1f32).abs() < 0.1f32);
+        assert!((semitone(1.97f32) - 2f32).abs() < 0.1f32);
+    }
+}
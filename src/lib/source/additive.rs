@@ -0,0 +1,58 @@
+use std::f32::consts::PI;
+
+use crate::note::Note;
+use crate::score::{ScorePartial, ScorePartSource};
+use crate::source::{NoteRequest, SoundSource};
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+pub struct Additive {
+    partials: Vec<ScorePartial>,
+}
+
+impl Additive {
+    pub fn new(partials: Vec<ScorePartial>) -> Self {
+        Self {
+            partials,
+        }
+    }
+
+    fn render(&self, request: &NoteRequest) -> Vec<f32> {
+        let sample_rate = request.sample_rate() as f32;
+        let mut data = vec![0f32; request.length()];
+        for partial in self.partials.iter() {
+            let cents = partial.detune().unwrap_or(0f32);
+            let frequency = request.frequency() * partial.harmonic() * 2f32.powf(cents / 1200f32);
+            // Partials above Nyquist would alias back into the audible range.
+            if frequency <= 0f32 || frequency >= sample_rate / 2f32 {
+                continue;
+            }
+            let dt = frequency / sample_rate;
+            let mut phase = partial.phase().unwrap_or(0f32).fract();
+            for d in data.iter_mut() {
+                *d += partial.amplitude() * (2.0 * PI * phase).sin();
+                phase = (phase + dt).fract();
+            }
+        }
+
+        data
+    }
+}
+
+impl SoundSource for Additive {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        Note::new(self.render(&request), DEFAULT_SAMPLE_RATE, None)
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::Additive(self.partials.clone())
+    }
+
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        Some(Note::new(self.render(request), request.sample_rate(), None))
+    }
+}
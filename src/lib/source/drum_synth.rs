@@ -0,0 +1,156 @@
+use std::cell::RefCell;
+use std::f32::consts::PI;
+
+use rand_chacha::ChaCha8Rng;
+
+use crate::filter::Biquad;
+use crate::note::Note;
+use crate::score::{DrumVoice, ScoreDrumVoice, ScorePartSource};
+use crate::source::{NoteRequest, SoundSource};
+use crate::source::noise::{seeded_rng, white_noise};
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+// Frequencies of the six square oscillators of a TR-808 cymbal.
+const METALLIC_FREQUENCIES: [f32; 6] = [205.3, 304.4, 369.6, 522.7, 540.0, 800.0];
+const HAT_BAND_FREQUENCY: f32 = 10_000f32;
+const SNARE_NOISE_FREQUENCY: f32 = 1_500f32;
+const CLAP_BAND_FREQUENCY: f32 = 1_200f32;
+const CLAP_BURST_SECONDS: f32 = 0.01f32;
+const CLAP_NUM_BURSTS: usize = 3;
+// Make up for the energy removed by the band filters of noise based voices.
+const HAT_GAIN: f32 = 3f32;
+const CLAP_GAIN: f32 = 4f32;
+
+fn decay_gain(t: f32, decay: f32) -> f32 {
+    (-t / decay.max(f32::EPSILON)).exp()
+}
+
+/// Sine whose frequency falls exponentially from `frequency * sweep` to `frequency`.
+fn swept_sine(frequency: f32, sweep: f32, sweep_time: f32, decay: f32, length: usize, sample_rate: u32) -> Vec<f32> {
+    let mut phase = 0f32;
+    (0..length).map(|n| {
+        let t = n as f32 / sample_rate as f32;
+        let current = frequency * (1f32 + (sweep - 1f32) * decay_gain(t, sweep_time));
+        let sample = (2.0 * PI * phase).sin() * decay_gain(t, decay);
+        phase = (phase + current / sample_rate as f32).fract();
+        sample
+    }).collect()
+}
+
+fn snare(frequency: f32, noise: f32, decay: f32, length: usize, sample_rate: u32, rng: &mut ChaCha8Rng) -> Vec<f32> {
+    let tone = swept_sine(frequency, 1.5, 0.01, decay * 0.5, length, sample_rate);
+    let mut filter = Biquad::high_pass(SNARE_NOISE_FREQUENCY, 0.7, sample_rate);
+    tone.iter().enumerate().map(|(n, &tone)| {
+        let t = n as f32 / sample_rate as f32;
+        let noise_sample = filter.process(white_noise(rng)) * decay_gain(t, decay);
+        tone * (1f32 - noise) + noise_sample * noise
+    }).collect()
+}
+
+fn hi_hat(decay: f32, length: usize, sample_rate: u32, rng: &mut ChaCha8Rng) -> Vec<f32> {
+    let mut phases = [0f32; METALLIC_FREQUENCIES.len()];
+    let mut filter = Biquad::band_pass(HAT_BAND_FREQUENCY, 1.0, sample_rate);
+    let mut high_pass = Biquad::high_pass(HAT_BAND_FREQUENCY * 0.7, 0.7, sample_rate);
+    (0..length).map(|n| {
+        let t = n as f32 / sample_rate as f32;
+        let mut metallic = 0f32;
+        for (phase, &frequency) in phases.iter_mut().zip(METALLIC_FREQUENCIES.iter()) {
+            metallic += if *phase < 0.5 { 1f32 } else { -1f32 };
+            *phase = (*phase + frequency / sample_rate as f32).fract();
+        }
+        let sample = 0.5 * metallic / METALLIC_FREQUENCIES.len() as f32 + 0.5 * white_noise(rng);
+        HAT_GAIN * high_pass.process(filter.process(sample)) * decay_gain(t, decay)
+    }).collect()
+}
+
+fn clap(decay: f32, length: usize, sample_rate: u32, rng: &mut ChaCha8Rng) -> Vec<f32> {
+    let mut filter = Biquad::band_pass(CLAP_BAND_FREQUENCY, 2.0, sample_rate);
+    let tail_start = CLAP_BURST_SECONDS * CLAP_NUM_BURSTS as f32;
+    (0..length).map(|n| {
+        let t = n as f32 / sample_rate as f32;
+        let gain = if t < tail_start {
+            decay_gain(t % CLAP_BURST_SECONDS, CLAP_BURST_SECONDS * 0.3)
+        } else {
+            decay_gain(t - tail_start, decay)
+        };
+        CLAP_GAIN * filter.process(white_noise(rng)) * gain
+    }).collect()
+}
+
+pub struct DrumSynth {
+    voices: Option<Vec<ScoreDrumVoice>>,
+    seed: Option<u64>,
+    kit: Vec<ScoreDrumVoice>,
+    rng: RefCell<ChaCha8Rng>,
+}
+
+impl DrumSynth {
+    pub fn new(voices: Option<Vec<ScoreDrumVoice>>, seed: Option<u64>) -> Self {
+        Self {
+            kit: voices.clone().unwrap_or_else(Self::default_kit),
+            voices,
+            seed,
+            rng: RefCell::new(seeded_rng(seed)),
+        }
+    }
+
+    /// Kick, snare, clap, closed hat, open hat and low, mid and high toms on semitones 0 to 7.
+    pub fn default_kit() -> Vec<ScoreDrumVoice> {
+        vec![
+            ScoreDrumVoice::new(0, DrumVoice::Kick { frequency: 50f32, sweep: 4f32, sweep_time: 0.04f32, decay: 0.3f32 }),
+            ScoreDrumVoice::new(1, DrumVoice::Snare { frequency: 180f32, noise: 0.6f32, decay: 0.15f32 }),
+            ScoreDrumVoice::new(2, DrumVoice::Clap { decay: 0.12f32 }),
+            ScoreDrumVoice::new(3, DrumVoice::HiHat { decay: 0.04f32 }),
+            ScoreDrumVoice::new(4, DrumVoice::HiHat { decay: 0.3f32 }),
+            ScoreDrumVoice::new(5, DrumVoice::Tom { frequency: 90f32, sweep: 1.6f32, decay: 0.35f32 }),
+            ScoreDrumVoice::new(6, DrumVoice::Tom { frequency: 130f32, sweep: 1.6f32, decay: 0.3f32 }),
+            ScoreDrumVoice::new(7, DrumVoice::Tom { frequency: 180f32, sweep: 1.6f32, decay: 0.25f32 }),
+        ]
+    }
+
+    fn render(&self, voice: &DrumVoice, length: usize, sample_rate: u32) -> Vec<f32> {
+        let mut rng = self.rng.borrow_mut();
+        match *voice {
+            DrumVoice::Kick { frequency, sweep, sweep_time, decay } => {
+                swept_sine(frequency, sweep, sweep_time, decay, length, sample_rate)
+            },
+            DrumVoice::Snare { frequency, noise, decay } => {
+                snare(frequency, noise.clamp(0f32, 1f32), decay, length, sample_rate, &mut rng)
+            },
+            DrumVoice::HiHat { decay } => hi_hat(decay, length, sample_rate, &mut rng),
+            DrumVoice::Clap { decay } => clap(decay, length, sample_rate, &mut rng),
+            DrumVoice::Tom { frequency, sweep, decay } => {
+                swept_sine(frequency, sweep, 0.05, decay, length, sample_rate)
+            },
+        }
+    }
+}
+
+impl SoundSource for DrumSynth {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        self.render_note(&request).unwrap_or_else(|| self.get_rest(DEFAULT_SAMPLE_RATE))
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::DrumSynth {
+            voices: self.voices.clone(),
+            seed: self.seed,
+        }
+    }
+
+    /// Plays the voice mapped to the nearest semitone, or silence if none is mapped.
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        let key = request.semitone().round() as i32;
+        let data = match self.kit.iter().find(|voice| voice.key() == key) {
+            Some(voice) => self.render(voice.voice(), request.length(), request.sample_rate()),
+            None => vec![0f32; request.length()],
+        };
+
+        Some(Note::new(data, request.sample_rate(), None))
+    }
+}
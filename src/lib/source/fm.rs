@@ -0,0 +1,196 @@
+use std::error::Error;
+use std::f32::consts::PI;
+
+use crate::envelope::Envelope;
+use crate::error::SourceError;
+use crate::note::Note;
+use crate::score::{FmAlgorithm, ScoreFmOperator, ScorePartSource};
+use crate::source::{NoteRequest, SoundSource};
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+// Phase deviation in radians caused by a modulator at level 1.0.
+const MODULATION_DEPTH: f32 = 2.0 * PI;
+
+struct FmOperator {
+    ratio: f32,
+    level: f32,
+    feedback: f32,
+    envelope: Option<Envelope>,
+}
+
+impl From<&ScoreFmOperator> for FmOperator {
+    fn from(score_operator: &ScoreFmOperator) -> Self {
+        Self {
+            ratio: score_operator.ratio(),
+            level: score_operator.level(),
+            feedback: score_operator.feedback().unwrap_or(0f32),
+            envelope: score_operator.envelope().clone().map(|envelope| envelope.into()),
+        }
+    }
+}
+
+/// Returns the `(modulator, target)` pairs and the carriers of `algorithm` for `num_operators` operators.
+fn routing(algorithm: &FmAlgorithm, num_operators: usize) -> (Vec<(usize, usize)>, Vec<usize>) {
+    match algorithm {
+        FmAlgorithm::Stack => (
+            (1..num_operators).map(|i| (i, i - 1)).collect(),
+            vec![0],
+        ),
+        FmAlgorithm::Parallel => (
+            vec![],
+            (0..num_operators).collect(),
+        ),
+        FmAlgorithm::Pairs => (
+            (1..num_operators).step_by(2).map(|i| (i, i - 1)).collect(),
+            (0..num_operators).step_by(2).collect(),
+        ),
+        FmAlgorithm::Branch => (
+            (1..num_operators).map(|i| (i, 0)).collect(),
+            vec![0],
+        ),
+        FmAlgorithm::Custom { routes, carriers } => (routes.clone(), carriers.clone()),
+    }
+}
+
+pub struct Fm {
+    score_operators: Vec<ScoreFmOperator>,
+    algorithm: FmAlgorithm,
+    operators: Vec<FmOperator>,
+    modulators: Vec<Vec<usize>>,
+    carriers: Vec<usize>,
+}
+
+impl Fm {
+    pub const MIN_OPERATORS: usize = 2;
+    pub const MAX_OPERATORS: usize = 6;
+
+    pub fn new(score_operators: Vec<ScoreFmOperator>, algorithm: FmAlgorithm) -> Result<Self, Box<dyn Error>> {
+        let num_operators = score_operators.len();
+        if !(Self::MIN_OPERATORS..=Self::MAX_OPERATORS).contains(&num_operators) {
+            return Err(Box::new(SourceError::new(format!(
+                "FM needs {} to {} operators, got {}", Self::MIN_OPERATORS, Self::MAX_OPERATORS, num_operators
+            ))));
+        }
+
+        let (routes, carriers) = routing(&algorithm, num_operators);
+        let mut modulators = vec![vec![]; num_operators];
+        for (modulator, target) in routes {
+            if modulator >= num_operators || modulator <= target {
+                return Err(Box::new(SourceError::new(format!(
+                    "FM operator {} cannot modulate operator {}", modulator, target
+                ))));
+            }
+            modulators[target].push(modulator);
+        }
+        if carriers.is_empty() || carriers.iter().any(|&carrier| carrier >= num_operators) {
+            return Err(Box::new(SourceError::new("FM carriers must be existing operators".to_string())));
+        }
+
+        Ok(Self {
+            operators: score_operators.iter().map(|operator| operator.into()).collect(),
+            score_operators,
+            algorithm,
+            modulators,
+            carriers,
+        })
+    }
+
+    fn render(&self, request: &NoteRequest) -> Vec<f32> {
+        let num_operators = self.operators.len();
+        let sample_rate = request.sample_rate() as f32;
+        let mut phases = vec![0f32; num_operators];
+        let mut outputs = vec![0f32; num_operators];
+        let mut previous_outputs = vec![0f32; num_operators];
+
+        let mut data = Vec::with_capacity(request.length());
+        for n in 0..request.length() {
+            let t = n as f32 / sample_rate;
+            // Modulators always have a higher index than their targets.
+            for i in (0..num_operators).rev() {
+                let operator = &self.operators[i];
+                let modulation = self.modulators[i].iter().map(|&m| outputs[m]).sum::<f32>()
+                    + operator.feedback * 0.5 * (outputs[i] + previous_outputs[i]);
+                let gain = operator.level * match operator.envelope.as_ref() {
+                    Some(envelope) => envelope.value(t, request.note_off()),
+                    None => 1f32,
+                };
+                previous_outputs[i] = outputs[i];
+                outputs[i] = gain * (2.0 * PI * phases[i] + MODULATION_DEPTH * modulation).sin();
+
+                phases[i] = (phases[i] + request.frequency() * operator.ratio / sample_rate).fract();
+            }
+            let sum = self.carriers.iter().map(|&c| outputs[c]).sum::<f32>();
+            data.push(sum / self.carriers.len() as f32);
+        }
+
+        data
+    }
+}
+
+impl SoundSource for Fm {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        Note::new(self.render(&request), DEFAULT_SAMPLE_RATE, None)
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::Fm {
+            operators: self.score_operators.clone(),
+            algorithm: self.algorithm.clone(),
+        }
+    }
+
+    /// Longest release among the envelopes of the carriers.
+    fn release(&self) -> f32 {
+        self.carriers.iter()
+            .filter_map(|&carrier| self.operators[carrier].envelope.as_ref())
+            .fold(0f32, |a, envelope| a.max(envelope.release()))
+    }
+
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        Some(Note::new(self.render(request), request.sample_rate(), None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::score::ScoreEnvelope;
+
+    // Mean slope over `seconds`, which grows with the sidebands added by the modulators.
+    fn brightness(data: &[f32], seconds: (f32, f32), sample_rate: u32) -> f32 {
+        let (start, end) = ((seconds.0 * sample_rate as f32) as usize, (seconds.1 * sample_rate as f32) as usize);
+        data[start..end].windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum::<f32>() / (end - start) as f32
+    }
+
+    #[test]
+    fn modulator_envelope_releases_after_note_off() {
+        let fm = Fm::new(vec![
+            ScoreFmOperator::new(1f32, 1f32, None, None),
+            ScoreFmOperator::new(3f32, 1f32, None, Some(ScoreEnvelope::new(0f32, 0f32, 1f32, 0.5))),
+        ], FmAlgorithm::Stack).unwrap();
+        let request = NoteRequest::new(0f32, 2f32, DEFAULT_SAMPLE_RATE).with_note_off(1f32);
+        let data = fm.render(&request);
+
+        let held = brightness(&data, (0.8, 0.9), DEFAULT_SAMPLE_RATE);
+        let releasing = brightness(&data, (1.2, 1.3), DEFAULT_SAMPLE_RATE);
+        let released = brightness(&data, (1.6, 1.7), DEFAULT_SAMPLE_RATE);
+        assert!(releasing < held);
+        assert!(released < releasing);
+        assert!(released < 0.5 * held);
+    }
+
+    #[test]
+    fn release_follows_carrier_envelopes() {
+        let fm = Fm::new(vec![
+            ScoreFmOperator::new(1f32, 1f32, None, Some(ScoreEnvelope::new(0f32, 0f32, 1f32, 0.3))),
+            ScoreFmOperator::new(3f32, 1f32, None, Some(ScoreEnvelope::new(0f32, 0f32, 1f32, 0.5))),
+        ], FmAlgorithm::Stack).unwrap();
+
+        assert_eq!(fm.release(), 0.3);
+    }
+}
@@ -0,0 +1,113 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::f32::consts::PI;
+
+use rand_chacha::ChaCha8Rng;
+
+use crate::error::SourceError;
+use crate::note::Note;
+use crate::score::{LfoTarget, ScoreGranular, ScorePartSource};
+use crate::source::{NoteRequest, SoundSource};
+use crate::source::noise::{seeded_rng, white_noise};
+use crate::source::sampler::read_root;
+use crate::utils::read_wav;
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+const MIN_GRAIN_LENGTH: usize = 2;
+
+pub struct Granular {
+    score: ScoreGranular,
+    channels: Vec<Vec<f32>>,
+    sample_rate: u32,
+    root: f32,
+    rng: RefCell<ChaCha8Rng>,
+}
+
+impl Granular {
+    pub const DEFAULT_GRAIN_SIZE: f32 = 0.1f32;
+    pub const DEFAULT_DENSITY: f32 = 20f32;
+    pub const DEFAULT_POSITION: f32 = 0.5f32;
+    pub const DEFAULT_POSITION_JITTER: f32 = 0f32;
+    pub const DEFAULT_PITCH_JITTER: f32 = 0f32;
+
+    pub fn new(score: ScoreGranular) -> Result<Self, Box<dyn Error>> {
+        let (channels, sample_rate) = read_wav(score.path())?;
+        if channels[0].len() < MIN_GRAIN_LENGTH {
+            return Err(Box::new(SourceError::new(format!("{} is too short for grains", score.path().display()))));
+        }
+        let root = read_root(score.path())?.unwrap_or(0f32);
+
+        Ok(Self {
+            rng: RefCell::new(seeded_rng(score.seed())),
+            score,
+            channels,
+            sample_rate,
+            root,
+        })
+    }
+
+    /// Overlap-adds Hann-windowed grains read around `position`, each started at a random
+    /// time within its slot so that regular spacing does not color the texture.
+    fn render(&self, request: &NoteRequest) -> Vec<Vec<f32>> {
+        let grain_size = self.score.grain_size().unwrap_or(Self::DEFAULT_GRAIN_SIZE).max(0f32);
+        let density = self.score.density().unwrap_or(Self::DEFAULT_DENSITY).max(f32::EPSILON);
+        let position = self.score.position().unwrap_or(Self::DEFAULT_POSITION);
+        let position_jitter = self.score.position_jitter().unwrap_or(Self::DEFAULT_POSITION_JITTER);
+        let pitch_jitter = self.score.pitch_jitter().unwrap_or(Self::DEFAULT_PITCH_JITTER);
+
+        let length = request.length();
+        let sample_rate = request.sample_rate() as f32;
+        let file_len = self.channels[0].len();
+        let grain_len = ((grain_size * sample_rate) as usize).max(MIN_GRAIN_LENGTH);
+        let window: Vec<f32> = (0..grain_len).map(|n| (PI * n as f32 / grain_len as f32).sin().powi(2)).collect();
+        let interval = sample_rate / density;
+        // Uncorrelated grains add up in power, so the loudness barely depends on the density.
+        let gain = 1f32 / (grain_size * density).max(1f32).sqrt();
+
+        let mut rng = self.rng.borrow_mut();
+        let mut outputs = vec![vec![0f32; length]; self.channels.len()];
+        let mut slot = 0f32;
+        while (slot as usize) < length {
+            let start = (slot + interval * (white_noise(&mut rng) + 1f32) / 2f32) as usize;
+            let moved = position + request.lfo(LfoTarget::Position, start as f32 / sample_rate);
+            let center = (moved + position_jitter * white_noise(&mut rng)).clamp(0f32, 1f32) * file_len as f32;
+            let detune = pitch_jitter * white_noise(&mut rng);
+            let step = 2f32.powf((request.semitone() - self.root + detune) / 12f32) * self.sample_rate as f32 / sample_rate;
+            let read_start = center - step * grain_len as f32 / 2f32;
+
+            for (n, w) in window.iter().enumerate().take(length.saturating_sub(start)) {
+                let read = read_start + n as f32 * step;
+                if read < 0f32 || read >= (file_len - 1) as f32 {
+                    continue;
+                }
+                let i = read as usize;
+                let frac = read - i as f32;
+                for (output, data) in outputs.iter_mut().zip(self.channels.iter()) {
+                    output[start + n] += gain * w * (data[i] + (data[i + 1] - data[i]) * frac);
+                }
+            }
+            slot += interval;
+        }
+
+        outputs
+    }
+}
+
+impl SoundSource for Granular {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        Note::from_channels(self.render(&request), DEFAULT_SAMPLE_RATE, None)
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::Granular(self.score.clone())
+    }
+
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        Some(Note::from_channels(self.render(request), request.sample_rate(), None))
+    }
+}
@@ -1,63 +1,136 @@
-use rand;
+use std::cell::RefCell;
+
 use rand_chacha::ChaCha8Rng;
-use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::rand_core::RngCore;
 
 use crate::note::Note;
-use crate::score::ScorePartSource;
-use crate::source::SoundSource;
+use crate::score::{ScoreKarpusStrong, ScorePartSource};
+use crate::source::{NoteRequest, SoundSource};
+use crate::source::noise::{seeded_rng, white_noise};
 
 
 const DEFAULT_SAMPLE_RATE: u32 = 48_000;
 const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
-const FREQUENCY_C4: f32 = 261.6256f32;
 
-fn create_karpus_strong_note(frequency: f32, duration: f32, sample_rate: u32, rng: &mut ChaCha8Rng) -> Note {
-    let num_samples = (duration * sample_rate as f32) as usize;
-    let buffer_size = (sample_rate as f32 / frequency) as usize;
+const DEFAULT_BRIGHTNESS: f32 = 1.0f32;
+const DEFAULT_STRETCH: f32 = 0.5f32;
+const DEFAULT_DRUM: f32 = 0.0f32;
+// Keeps the fractional delay of the tuning allpass in its well-behaved range.
+const MIN_ALLPASS_DELAY: f32 = 0.1f32;
+// Decay of 60 dB used to define `decay` seconds.
+const DECAY_LEVEL: f32 = 0.001f32;
+
+struct KarpusStrongParameters {
+    decay: Option<f32>,
+    brightness: f32,
+    stretch: f32,
+    pick_position: Option<f32>,
+    drum: f32,
+}
 
-    let mut buffer: Vec<f32> = (0..buffer_size)
-        .map(|_| rng.next_u64() as f32 / u64::MAX as f32 * 2.0 - 1.0)
+impl From<&ScoreKarpusStrong> for KarpusStrongParameters {
+    fn from(score: &ScoreKarpusStrong) -> Self {
+        Self {
+            decay: score.decay(),
+            brightness: score.brightness().unwrap_or(DEFAULT_BRIGHTNESS).clamp(0.01, 1f32),
+            stretch: score.stretch().unwrap_or(DEFAULT_STRETCH).clamp(0.01, 0.99),
+            pick_position: score.pick_position().map(|position| position.clamp(0f32, 1f32)),
+            drum: score.drum().unwrap_or(DEFAULT_DRUM).clamp(0f32, 1f32),
+        }
+    }
+}
+
+fn create_excitation(parameters: &KarpusStrongParameters, len: usize, rng: &mut ChaCha8Rng) -> Vec<f32> {
+    let mut low_passed = 0f32;
+    let mut excitation: Vec<f32> = (0..len)
+        .map(|_| {
+            low_passed += parameters.brightness * (white_noise(rng) - low_passed);
+            low_passed
+        })
         .collect();
 
-    let mut output = Vec::with_capacity(num_samples);
+    // Plucking at a fraction of the string cancels the harmonics with a node there.
+    if let Some(pick_position) = parameters.pick_position {
+        let offset = (pick_position * len as f32).round() as usize;
+        if 0 < offset && offset < len {
+            let original = excitation.clone();
+            for (i, e) in excitation.iter_mut().enumerate().skip(offset) {
+                *e = original[i] - original[i - offset];
+            }
+        }
+    }
 
-    for i in 0..num_samples {
-        let sample = buffer[i % buffer_size];
-        output.push(sample);
+    excitation
+}
 
-        let next = 0.5 * (buffer[i % buffer_size] + buffer[(i+1) % buffer_size]);
-        buffer[i % buffer_size] = next;
+fn create_karpus_strong_data(parameters: &KarpusStrongParameters, request: &NoteRequest, rng: &mut ChaCha8Rng) -> Vec<f32> {
+    let period = request.sample_rate() as f32 / request.frequency();
+    let delay = (period - parameters.stretch).max(2f32 + MIN_ALLPASS_DELAY);
+    let buffer_size = (delay - MIN_ALLPASS_DELAY).floor() as usize;
+    let fraction = delay - buffer_size as f32;
+    let allpass = (1f32 - fraction) / (1f32 + fraction);
+    let loss = match parameters.decay {
+        Some(decay) => DECAY_LEVEL.powf(1f32 / (decay.max(f32::EPSILON) * request.frequency())),
+        None => 1f32,
+    };
+
+    let mut buffer = create_excitation(parameters, buffer_size, rng);
+    let mut last = 0f32;
+    let mut allpass_input = 0f32;
+    let mut allpass_output = 0f32;
+
+    let mut output = Vec::with_capacity(request.length());
+    for i in 0..request.length() {
+        let index = i % buffer_size;
+        let current = buffer[index];
+        output.push(current);
+
+        let mut averaged = (1f32 - parameters.stretch) * current + parameters.stretch * last;
+        last = current;
+        if parameters.drum > 0f32 && (rng.next_u64() as f32 / u64::MAX as f32) < parameters.drum {
+            averaged = -averaged;
+        }
+        allpass_output = allpass * averaged + allpass_input - allpass * allpass_output;
+        allpass_input = averaged;
+
+        buffer[index] = loss * allpass_output;
     }
 
-    Note::new(output, sample_rate, None)
+    output
 }
 
 pub struct KarpusStrong {
-    seed: Option<u64>,
-    note: Note,
+    score: ScoreKarpusStrong,
+    parameters: KarpusStrongParameters,
+    rng: RefCell<ChaCha8Rng>,
 }
 
 impl KarpusStrong {
-    pub fn new(seed: Option<u64>) -> Self {
-        let mut rng = match seed {
-            Some(seed) => ChaCha8Rng::seed_from_u64(seed),
-            None => ChaCha8Rng::from_seed(Default::default()),
-        };
-        let note = create_karpus_strong_note(FREQUENCY_C4, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE, &mut rng);
-
+    pub fn new(score: ScoreKarpusStrong) -> Self {
         Self {
-            seed,
-            note,
+            parameters: (&score).into(),
+            rng: RefCell::new(seeded_rng(score.seed())),
+            score,
         }
     }
 }
 
 impl SoundSource for KarpusStrong {
     fn get_base(&self) -> Note {
-        self.note.to_owned()
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+        let data = create_karpus_strong_data(&self.parameters, &request, &mut self.rng.borrow_mut());
+
+        Note::new(data, DEFAULT_SAMPLE_RATE, None)
     }
 
     fn to_score_part_source(&self) -> ScorePartSource {
-        ScorePartSource::KarpusStrong(self.seed)
+        ScorePartSource::KarpusStrong(self.score.clone())
+    }
+
+    /// Plucks the string at the note's own frequency with a fresh excitation from the seeded stream.
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        let data = create_karpus_strong_data(&self.parameters, request, &mut self.rng.borrow_mut());
+
+        Some(Note::new(data, request.sample_rate(), None))
     }
 }
@@ -1,64 +1,223 @@
+pub mod additive;
+pub mod drum_synth;
+pub mod fm;
+pub mod granular;
 pub mod karplus_strong;
+pub mod modal;
+pub mod multi_sampler;
+pub mod noise;
+pub mod pulse;
 pub mod sampler;
+pub mod saw;
+pub mod sfz;
 pub mod sin;
+pub mod sliced_sampler;
+pub mod sound_font;
+pub mod square;
+pub mod subtractive;
 pub mod triangle;
+pub mod wavetable;
 
+use crate::lfo::{lfo_offset, LfoRoute};
 use crate::note::Note;
-use crate::score::ScorePartSource;
+use crate::score::{LfoTarget, ScorePartSource, ScorePlayback};
 use crate::utils::resample_linear;
 
 
+pub const FREQUENCY_C4: f32 = 261.6256f32;
+/// MIDI note number of semitone 0.
+pub const MIDI_NOTE_C4: i32 = 60;
+pub const DEFAULT_VELOCITY: f32 = 1.0f32;
+
 const FADE_RATE: f32 = 0.005f32;
 const FADE_SECONDS_MIN: f32 = 0.002f32;
 
+pub fn semitone_to_frequency(semitone: f32) -> f32 {
+    FREQUENCY_C4 * 2f32.powf(semitone / 12f32)
+}
+
+pub fn frequency_to_semitone(frequency: f32) -> f32 {
+    12f32 * (frequency / FREQUENCY_C4).log2()
+}
+
+/// A note to be rendered directly by a source at its final pitch and sample rate.
+#[derive(Clone)]
+pub struct NoteRequest {
+    semitone: f32,
+    seconds: f32,
+    note_off: Option<f32>,
+    sample_rate: u32,
+    velocity: f32,
+    playback: Option<ScorePlayback>,
+    lfos: Vec<LfoRoute>,
+}
+
+impl NoteRequest {
+    pub fn new(semitone: f32, seconds: f32, sample_rate: u32) -> Self {
+        Self {
+            semitone,
+            seconds,
+            note_off: None,
+            sample_rate,
+            velocity: DEFAULT_VELOCITY,
+            playback: None,
+            lfos: vec![],
+        }
+    }
+
+    pub fn with_note_off(self, note_off: f32) -> Self {
+        Self {
+            note_off: Some(note_off),
+            ..self
+        }
+    }
+
+    pub fn with_velocity(self, velocity: f32) -> Self {
+        Self {
+            velocity,
+            ..self
+        }
+    }
+
+    pub fn with_playback(self, playback: Option<ScorePlayback>) -> Self {
+        Self {
+            playback,
+            ..self
+        }
+    }
+
+    pub fn with_lfos(self, lfos: Vec<LfoRoute>) -> Self {
+        Self {
+            lfos,
+            ..self
+        }
+    }
+
+    pub fn semitone(&self) -> f32 {
+        self.semitone
+    }
+
+    pub fn frequency(&self) -> f32 {
+        semitone_to_frequency(self.semitone)
+    }
+
+    pub fn seconds(&self) -> f32 {
+        self.seconds
+    }
+
+    /// Seconds after note-on at which the note is released. Notes without one are held throughout.
+    pub fn note_off(&self) -> f32 {
+        self.note_off.unwrap_or(self.seconds)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn length(&self) -> usize {
+        (self.seconds * self.sample_rate as f32) as usize
+    }
+
+    pub fn velocity(&self) -> f32 {
+        self.velocity
+    }
+
+    /// Playback overriding the source's for this note. Only samplers use it.
+    pub fn playback(&self) -> Option<ScorePlayback> {
+        self.playback
+    }
+
+    /// Offset of a source parameter by the note's LFOs at `t` seconds after note-on.
+    pub fn lfo(&self, target: LfoTarget, t: f32) -> f32 {
+        lfo_offset(&self.lfos, target, t)
+    }
+}
+
+/// Renders a periodic waveform by accumulating phase at the requested frequency.
+/// `wave` receives the phase in [0, 1) and the phase increment per sample.
+pub fn oscillate(request: &NoteRequest, wave: impl Fn(f32, f32) -> f32) -> Vec<f32> {
+    oscillate_with_time(request, |phase, dt, _| wave(phase, dt))
+}
+
+/// Like `oscillate`, with the seconds since note-on as the third argument of `wave`.
+pub fn oscillate_with_time(request: &NoteRequest, wave: impl Fn(f32, f32, f32) -> f32) -> Vec<f32> {
+    let dt = request.frequency() / request.sample_rate() as f32;
+    let mut phase = 0f32;
+    let mut data = Vec::with_capacity(request.length());
+    for n in 0..request.length() {
+        data.push(wave(phase, dt, n as f32 / request.sample_rate() as f32));
+
+        phase += dt;
+        if phase >= 1f32 {
+            phase -= phase.floor();
+        }
+    }
+
+    data
+}
+
 fn fit_length(note: Note, target_seconds: f32) -> Note {
     let target_len = (target_seconds * note.sample_rate() as f32) as usize;
-    if note.data().is_empty() {
-        return Note::new(vec![0f32; target_len], note.sample_rate(), note.envelope().to_owned());
-    }
-    if note.data().len() >= target_len {
-        let mut data = note.data().clone();
-        data.truncate(target_len);
-        return Note::new(data, note.sample_rate(), note.envelope().to_owned());
-    }
-
-    let mut out = Vec::with_capacity(target_len);
-    while out.len() < target_len {
-        let remain = target_len - out.len();
-        if remain >= note.data().len() {
-            out.extend_from_slice(note.data());
-        } else {
-            out.extend_from_slice(&note.data()[..remain]);
+
+    note.map_channels(|data| {
+        if data.is_empty() {
+            return vec![0f32; target_len];
+        }
+        if data.len() >= target_len {
+            return data[..target_len].to_vec();
+        }
+
+        let mut out = Vec::with_capacity(target_len);
+        while out.len() < target_len {
+            let remain = target_len - out.len();
+            if remain >= data.len() {
+                out.extend_from_slice(data);
+            } else {
+                out.extend_from_slice(&data[..remain]);
+            }
         }
-    }
 
-    Note::new(out, note.sample_rate(), note.envelope().to_owned())
+        out
+    })
 }
 
 fn pitch_shift_semitones(note: Note, semitones: f32) -> Note {
     let factor = 2f32.powf(semitones / 12f32);
 
-    Note::new(
-        resample_linear(note.data().clone(), factor),
-        note.sample_rate(),
-        note.envelope().to_owned(),
-    )
+    note.map_channels(|data| resample_linear(data.clone(), factor))
 }
 
 pub fn fade_in_out(note: Note, fade_seconds: f32) -> Note {
     let fade_samples = (fade_seconds * note.sample_rate() as f32) as usize;
-    let n = note.data().len();
-    let mut data = note.data().clone();
-    for i in 0..fade_samples.min(n) {
-        let g = i as f32 / fade_samples.max(1) as f32;
-        data[i] *= g;
-    }
-    for i in 0..fade_samples.min(n) {
-        let g = i as f32 / fade_samples.max(1) as f32;
-        data[n-1-i] *= g;
-    }
 
-    Note::new(data, note.sample_rate(), note.envelope().to_owned())
+    note.map_channels(|data| {
+        let n = data.len();
+        let mut data = data.clone();
+        for i in 0..fade_samples.min(n) {
+            let g = i as f32 / fade_samples.max(1) as f32;
+            data[i] *= g;
+        }
+        for i in 0..fade_samples.min(n) {
+            let g = i as f32 / fade_samples.max(1) as f32;
+            data[n-1-i] *= g;
+        }
+
+        data
+    })
+}
+
+/// Polynomial band-limited step correction for a discontinuity at phase 0.
+/// `t` is the phase in [0, 1) and `dt` the phase increment per sample.
+pub fn poly_blep(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let t = t / dt;
+        t + t - t * t - 1f32
+    } else if t > 1f32 - dt {
+        let t = (t - 1f32) / dt;
+        t * t + t + t + 1f32
+    } else {
+        0f32
+    }
 }
 
 pub trait SoundSource {
@@ -66,10 +225,20 @@ pub trait SoundSource {
 
     fn to_score_part_source(&self) -> ScorePartSource;
 
-    fn get_rest(&self) -> Note {
+    /// Seconds the envelopes of the source's own notes keep sounding after note-off.
+    fn release(&self) -> f32 {
+        0f32
+    }
+
+    /// Whether notes in `playback` sound to their end whatever their length, so are released only after it.
+    fn is_one_shot(&self, _playback: Option<ScorePlayback>) -> bool {
+        false
+    }
+
+    fn get_rest(&self, sample_rate: u32) -> Note {
         Note::new(
             vec![0f32],
-            self.sample_rate(),
+            sample_rate,
             None,
         )
     }
@@ -78,18 +247,25 @@ pub trait SoundSource {
         self.get_base().sample_rate()
     }
 
-    fn get_note(&self, seconds: f32, semitones: Option<f32>) -> Note {
-        let note = match semitones {
-            Some(semitone) => {
-                let note = self.get_base();
-                pitch_shift_semitones(note, semitone)
-            },
-            None => self.get_rest(),
+    /// Renders the note directly at its pitch, length and sample rate.
+    /// Sources returning `None` are resolved by resampling and looping `get_base`.
+    fn render_note(&self, _request: &NoteRequest) -> Option<Note> {
+        None
+    }
+
+    fn get_note(&self, request: &NoteRequest) -> Note {
+        let note = match self.render_note(request) {
+            Some(note) => note,
+            None => fit_length(pitch_shift_semitones(self.get_base(), request.semitone()), request.seconds()),
         };
-        let note = fit_length(note, seconds);
+        let fade_seconds = (request.seconds() * FADE_RATE).max(FADE_SECONDS_MIN);
+
+        fade_in_out(note, fade_seconds)
+    }
+
+    fn get_rest_note(&self, seconds: f32, sample_rate: u32) -> Note {
         let fade_seconds = (seconds * FADE_RATE).max(FADE_SECONDS_MIN);
-        let note = fade_in_out(note, fade_seconds);
 
-        note
+        fade_in_out(fit_length(self.get_rest(sample_rate), seconds), fade_seconds)
     }
 }
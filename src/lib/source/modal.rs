@@ -0,0 +1,155 @@
+use std::cell::RefCell;
+use std::f32::consts::PI;
+
+use rand_chacha::ChaCha8Rng;
+
+use crate::note::Note;
+use crate::score::{ModalExcitation, ModalPreset, ScorePartSource};
+use crate::source::{NoteRequest, SoundSource};
+use crate::source::noise::{seeded_rng, white_noise};
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+/// `(ratio, amplitude, decay)` of each mode.
+fn preset_modes(preset: &ModalPreset) -> Vec<(f32, f32, f32)> {
+    match preset {
+        ModalPreset::Marimba => vec![
+            (1.0, 1.0, 0.5),
+            (3.99, 0.4, 0.15),
+            (10.65, 0.15, 0.05),
+        ],
+        ModalPreset::Vibraphone => vec![
+            (1.0, 1.0, 2.5),
+            (4.0, 0.35, 0.8),
+            (10.0, 0.1, 0.25),
+        ],
+        ModalPreset::Bell => vec![
+            (0.5, 0.6, 4.0),
+            (1.0, 1.0, 3.0),
+            (1.19, 0.7, 2.5),
+            (1.5, 0.5, 2.0),
+            (2.0, 0.6, 1.6),
+            (2.5, 0.3, 1.2),
+            (2.66, 0.35, 1.0),
+            (3.01, 0.25, 0.8),
+            (4.1, 0.2, 0.6),
+        ],
+        ModalPreset::Plate => vec![
+            (1.0, 1.0, 1.5),
+            (1.58, 0.8, 1.3),
+            (2.0, 0.7, 1.2),
+            (2.24, 0.6, 1.1),
+            (2.55, 0.5, 1.0),
+            (2.92, 0.45, 0.9),
+            (3.16, 0.4, 0.8),
+            (3.54, 0.35, 0.7),
+            (3.61, 0.3, 0.65),
+            (4.0, 0.25, 0.6),
+        ],
+        ModalPreset::Custom(modes) => modes.iter()
+            .map(|mode| (mode.ratio(), mode.amplitude(), mode.decay()))
+            .collect(),
+    }
+}
+
+/// Two-pole resonator ringing at `frequency` with an exponential decay time of `decay` seconds.
+struct Resonator {
+    a1: f32,
+    a2: f32,
+    gain: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Resonator {
+    fn new(frequency: f32, amplitude: f32, decay: f32, sample_rate: u32) -> Self {
+        let omega = 2.0 * PI * frequency / sample_rate as f32;
+        let r = (-1f32 / (decay.max(f32::EPSILON) * sample_rate as f32)).exp();
+        Self {
+            a1: 2.0 * r * omega.cos(),
+            a2: -r * r,
+            // An impulse then rings with a peak of about `amplitude`.
+            gain: amplitude * omega.sin(),
+            y1: 0f32,
+            y2: 0f32,
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.gain * x + self.a1 * self.y1 + self.a2 * self.y2;
+        self.y2 = self.y1;
+        self.y1 = y;
+
+        y
+    }
+}
+
+pub struct Modal {
+    preset: ModalPreset,
+    excitation: ModalExcitation,
+    seed: Option<u64>,
+    modes: Vec<(f32, f32, f32)>,
+    rng: RefCell<ChaCha8Rng>,
+}
+
+impl Modal {
+    pub fn new(preset: ModalPreset, excitation: ModalExcitation, seed: Option<u64>) -> Self {
+        Self {
+            modes: preset_modes(&preset),
+            preset,
+            excitation,
+            seed,
+            rng: RefCell::new(seeded_rng(seed)),
+        }
+    }
+
+    fn render(&self, request: &NoteRequest) -> Vec<f32> {
+        let sample_rate = request.sample_rate();
+        let nyquist = sample_rate as f32 / 2f32;
+        let mut resonators: Vec<Resonator> = self.modes.iter()
+            .filter(|(ratio, _, _)| request.frequency() * ratio < nyquist)
+            .map(|&(ratio, amplitude, decay)| Resonator::new(request.frequency() * ratio, amplitude, decay, sample_rate))
+            .collect();
+
+        let burst_length = match self.excitation {
+            ModalExcitation::Impulse => 1,
+            ModalExcitation::Noise { length } => ((length * sample_rate as f32) as usize).max(1),
+        };
+        let mut rng = self.rng.borrow_mut();
+        (0..request.length()).map(|n| {
+            let x = match self.excitation {
+                ModalExcitation::Impulse => if n == 0 { 1f32 } else { 0f32 },
+                ModalExcitation::Noise { .. } => if n < burst_length {
+                    // Hann-shaped burst, normalized to the energy of an impulse.
+                    let window = (PI * n as f32 / burst_length as f32).sin().powi(2);
+                    white_noise(&mut rng) * window * 2f32 / (burst_length as f32).sqrt()
+                } else {
+                    0f32
+                },
+            };
+            resonators.iter_mut().map(|resonator| resonator.process(x)).sum()
+        }).collect()
+    }
+}
+
+impl SoundSource for Modal {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        Note::new(self.render(&request), DEFAULT_SAMPLE_RATE, None)
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::Modal {
+            preset: self.preset.clone(),
+            excitation: self.excitation,
+            seed: self.seed,
+        }
+    }
+
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        Some(Note::new(self.render(request), request.sample_rate(), None))
+    }
+}
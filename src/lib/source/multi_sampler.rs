@@ -0,0 +1,111 @@
+use std::error::Error;
+use std::fs;
+
+use crate::error::SourceError;
+use crate::note::Note;
+use crate::score::{MultiSamplerZones, ScorePartSource, ScoreSampleZone, ScoreSampler};
+use crate::source::{NoteRequest, SoundSource};
+use crate::source::sampler::Sampler;
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+/// Distance from `value` to the range, or 0 inside it.
+fn range_distance(value: f32, (low, high): (f32, f32)) -> f32 {
+    if value < low {
+        low - value
+    } else if value > high {
+        value - high
+    } else {
+        0f32
+    }
+}
+
+fn read_zones(zones: &MultiSamplerZones) -> Result<Vec<ScoreSampleZone>, Box<dyn Error>> {
+    match zones {
+        MultiSamplerZones::File(path) => {
+            let json = fs::read_to_string(path)?;
+            let zones: Vec<ScoreSampleZone> = serde_json::from_str(&json)?;
+            let directory = path.parent().map(|parent| parent.to_path_buf()).unwrap_or_default();
+
+            Ok(zones.into_iter().map(|zone| {
+                let sampler = zone.sampler();
+                ScoreSampleZone::new(
+                    ScoreSampler::new(
+                        directory.join(sampler.path()),
+                        sampler.root_note(),
+                        sampler.fine_tune(),
+                        sampler.loop_start(),
+                        sampler.loop_end(),
+                        sampler.playback(),
+                        sampler.engine(),
+                    ),
+                    zone.keys(),
+                    zone.velocities(),
+                )
+            }).collect())
+        },
+        MultiSamplerZones::Zones(zones) => Ok(zones.clone()),
+    }
+}
+
+struct Zone {
+    keys: (f32, f32),
+    velocities: Option<(f32, f32)>,
+    sampler: Sampler,
+}
+
+pub struct MultiSampler {
+    zones: MultiSamplerZones,
+    samplers: Vec<Zone>,
+}
+
+impl MultiSampler {
+    pub fn new(zones: MultiSamplerZones) -> Result<Self, Box<dyn Error>> {
+        let mut samplers = vec![];
+        for zone in read_zones(&zones)? {
+            let (low, high) = zone.keys();
+            samplers.push(Zone {
+                keys: (low.min(high) as f32, low.max(high) as f32),
+                velocities: zone.velocities().map(|(low, high)| (low.min(high), low.max(high))),
+                sampler: Sampler::new(zone.sampler().clone())?,
+            });
+        }
+        if samplers.is_empty() {
+            return Err(Box::new(SourceError::new("multi sampler needs at least one zone".to_string())));
+        }
+
+        Ok(Self {
+            zones,
+            samplers,
+        })
+    }
+
+    /// Picks the zone nearest to the note's key, then nearest to its velocity.
+    fn find_zone(&self, semitone: f32, velocity: f32) -> &Zone {
+        let distance = |zone: &Zone| (
+            range_distance(semitone, zone.keys),
+            zone.velocities.map_or(0f32, |velocities| range_distance(velocity, velocities)),
+        );
+        self.samplers.iter()
+            .reduce(|nearest, zone| if distance(zone) < distance(nearest) { zone } else { nearest })
+            .expect("multi sampler has at least one zone")
+    }
+}
+
+impl SoundSource for MultiSampler {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        self.render_note(&request).unwrap_or_else(|| self.get_rest(DEFAULT_SAMPLE_RATE))
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::MultiSampler(self.zones.clone())
+    }
+
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        self.find_zone(request.semitone(), request.velocity()).sampler.render_note(request)
+    }
+}
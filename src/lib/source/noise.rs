@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+
+use rand_chacha::ChaCha8Rng;
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+
+use crate::filter::Biquad;
+use crate::note::Note;
+use crate::score::{NoiseColor, ScorePartSource};
+use crate::source::{NoteRequest, SoundSource};
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+pub fn seeded_rng(seed: Option<u64>) -> ChaCha8Rng {
+    match seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_seed(Default::default()),
+    }
+}
+
+/// Uniform white noise in [-1, 1].
+pub fn white_noise(rng: &mut ChaCha8Rng) -> f32 {
+    rng.next_u64() as f32 / u64::MAX as f32 * 2.0 - 1.0
+}
+
+fn create_noise_data(color: &NoiseColor, length: usize, rng: &mut ChaCha8Rng) -> Vec<f32> {
+    match color {
+        NoiseColor::White => (0..length).map(|_| white_noise(rng)).collect(),
+        NoiseColor::Pink => {
+            // Paul Kellet's refined pink noise filter.
+            let mut b = [0f32; 7];
+            (0..length).map(|_| {
+                let white = white_noise(rng);
+                b[0] = 0.99886 * b[0] + white * 0.0555179;
+                b[1] = 0.99332 * b[1] + white * 0.0750759;
+                b[2] = 0.96900 * b[2] + white * 0.153852;
+                b[3] = 0.86650 * b[3] + white * 0.3104856;
+                b[4] = 0.55000 * b[4] + white * 0.5329522;
+                b[5] = -0.7616 * b[5] - white * 0.0168980;
+                let pink = b.iter().sum::<f32>() + white * 0.5362;
+                b[6] = white * 0.115926;
+                pink * 0.11
+            }).collect()
+        },
+        NoiseColor::Brown => {
+            let mut last = 0f32;
+            (0..length).map(|_| {
+                last = (last + 0.02 * white_noise(rng)) / 1.02;
+                last * 3.5
+            }).collect()
+        },
+    }
+}
+
+pub struct Noise {
+    color: NoiseColor,
+    seed: Option<u64>,
+    resonance: Option<f32>,
+    rng: RefCell<ChaCha8Rng>,
+}
+
+impl Noise {
+    pub const DEFAULT_RESONANCE: f32 = 10f32;
+
+    pub fn new(color: NoiseColor, seed: Option<u64>, resonance: Option<f32>) -> Self {
+        Self {
+            color,
+            seed,
+            resonance,
+            rng: RefCell::new(seeded_rng(seed)),
+        }
+    }
+
+    fn render(&self, request: &NoteRequest) -> Vec<f32> {
+        let data = create_noise_data(&self.color, request.length(), &mut self.rng.borrow_mut());
+        match self.resonance {
+            Some(resonance) => {
+                let mut filter = Biquad::band_pass(request.frequency(), resonance, request.sample_rate());
+                data.into_iter().map(|d| filter.process(d)).collect()
+            },
+            None => data,
+        }
+    }
+}
+
+impl SoundSource for Noise {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        Note::new(self.render(&request), DEFAULT_SAMPLE_RATE, None)
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::Noise {
+            color: self.color.clone(),
+            seed: self.seed,
+            resonance: self.resonance,
+        }
+    }
+
+    /// Every note draws fresh noise from the seeded stream, so a score always renders the same.
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        Some(Note::new(self.render(request), request.sample_rate(), None))
+    }
+}
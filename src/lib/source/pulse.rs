@@ -0,0 +1,56 @@
+use crate::note::Note;
+use crate::score::{LfoTarget, ScorePartSource};
+use crate::source::{oscillate, oscillate_with_time, poly_blep, NoteRequest, SoundSource};
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+pub fn pulse_wave(phase: f32, dt: f32, width: f32) -> f32 {
+    let sample = if phase < width { 1f32 } else { -1f32 };
+
+    sample + poly_blep(phase, dt) - poly_blep((phase - width + 1f32) % 1f32, dt)
+}
+
+pub struct Pulse {
+    width: f32,
+}
+
+impl Pulse {
+    pub const DEFAULT_WIDTH: f32 = 0.25f32;
+
+    const MIN_WIDTH: f32 = 0.01f32;
+    const MAX_WIDTH: f32 = 0.99f32;
+
+    pub fn new(width: f32) -> Self {
+        Self {
+            width: width.clamp(Self::MIN_WIDTH, Self::MAX_WIDTH),
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+}
+
+impl SoundSource for Pulse {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+        let data = oscillate(&request, |phase, dt| pulse_wave(phase, dt, self.width));
+
+        Note::new(data, DEFAULT_SAMPLE_RATE, None)
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::Pulse { width: self.width }
+    }
+
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        let data = oscillate_with_time(request, |phase, dt, t| {
+            let width = (self.width + request.lfo(LfoTarget::Width, t)).clamp(Self::MIN_WIDTH, Self::MAX_WIDTH);
+            pulse_wave(phase, dt, width)
+        });
+
+        Some(Note::new(data, request.sample_rate(), None))
+    }
+}
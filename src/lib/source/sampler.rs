@@ -1,51 +1,483 @@
-use std::path::PathBuf;
-
-use hound::{WavReader, SampleFormat};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
 
+use crate::analysis::detect_pitch;
+use crate::envelope::Envelope;
+use crate::error::SourceError;
 use crate::note::Note;
-use crate::score::ScorePartSource;
-use crate::source::SoundSource;
+use crate::score::{PlaybackMode, ScorePartSource, ScorePlayback, ScoreSampler, StretchEngine};
+use crate::source::{fit_length, frequency_to_semitone, MIDI_NOTE_C4, NoteRequest, SoundSource};
+use crate::stretch::{pitch_shift, time_stretch};
+use crate::utils::{downmix_channels, read_wav, resample_data};
 
 
-pub struct Sampler {
-    src_file: PathBuf,
-    note: Note,
+const MIDI_MAX: f32 = 127f32;
+const DEFAULT_CROSSFADE_SECONDS: f32 = 0.01f32;
+const SMPL_UNITY_NOTE_OFFSET: usize = 12;
+const SMPL_PITCH_FRACTION_OFFSET: usize = 16;
+const SMPL_NUM_LOOPS_OFFSET: usize = 28;
+const SMPL_LOOPS_OFFSET: usize = 36;
+const SMPL_LOOP_START_OFFSET: usize = 8;
+const SMPL_LOOP_END_OFFSET: usize = 12;
+
+/// Tuning and sustain loop stored in the `smpl` chunk of a WAV file.
+struct SampleInfo {
+    root_note: i32,
+    fine_tune: f32,
+    loop_points: Option<(usize, usize)>,
 }
 
-impl Sampler {
-    pub fn new(src_file: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut reader = WavReader::open(src_file.to_owned())?;
-        let spec = reader.spec();
-
-        let data: Vec<f32> = if spec.sample_format == SampleFormat::Float {
-            reader
-                .samples::<f32>()
-                .map(|s| s.unwrap_or(0f32))
-                .collect()
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn parse_smpl_chunk(chunk: &[u8]) -> Option<SampleInfo> {
+    let unity_note = read_u32(chunk, SMPL_UNITY_NOTE_OFFSET)?;
+    let pitch_fraction = read_u32(chunk, SMPL_PITCH_FRACTION_OFFSET)?;
+    let loop_points = match read_u32(chunk, SMPL_NUM_LOOPS_OFFSET)? {
+        0 => None,
+        // Only the first loop is used as the sustain loop. Its end is inclusive.
+        _ => Some((
+            read_u32(chunk, SMPL_LOOPS_OFFSET + SMPL_LOOP_START_OFFSET)? as usize,
+            read_u32(chunk, SMPL_LOOPS_OFFSET + SMPL_LOOP_END_OFFSET)? as usize + 1,
+        )),
+    };
+
+    Some(SampleInfo {
+        root_note: unity_note as i32 - MIDI_NOTE_C4,
+        fine_tune: 100f32 * (pitch_fraction as f64 / (u32::MAX as f64 + 1f64)) as f32,
+        loop_points,
+    })
+}
+
+/// Sample info of the bytes of a WAV file. A `smpl` chunk cut short by the end of the file is an error.
+fn parse_sample_info(data: &[u8]) -> Result<Option<SampleInfo>, Box<dyn Error>> {
+    if data.get(0..4) != Some(b"RIFF") || data.get(8..12) != Some(b"WAVE") {
+        return Ok(None);
+    }
+
+    let mut offset = 12;
+    while let Some(size) = read_u32(data, offset + 4) {
+        let start = offset + 8;
+        let end = start.saturating_add(size as usize);
+        if &data[offset..offset + 4] == b"smpl" {
+            return match data.get(start..end).and_then(parse_smpl_chunk) {
+                Some(info) => Ok(Some(info)),
+                None => Err(Box::new(SourceError::new("truncated smpl chunk".to_string()))),
+            };
+        }
+        // Chunks are padded to an even size.
+        offset = end.min(data.len()) + (size as usize & 1);
+    }
+
+    Ok(None)
+}
+
+fn read_sample_info(src_file: &Path) -> Result<Option<SampleInfo>, Box<dyn Error>> {
+    parse_sample_info(&fs::read(src_file)?)
+}
+
+/// Semitone at which a WAV file plays at its recorded pitch, from its `smpl` chunk.
+pub fn read_root(src_file: &Path) -> Result<Option<f32>, Box<dyn Error>> {
+    Ok(read_sample_info(src_file)?.map(|info| info.root_note as f32 + info.fine_tune / 100f32))
+}
+
+/// Root note and fine tune in cents at which a WAV file plays at the pitch detected in it.
+/// None if the file has no clear pitch.
+pub fn detect_root(src_file: &Path) -> Result<Option<(i32, f32)>, Box<dyn Error>> {
+    let (channels, sample_rate) = read_wav(src_file)?;
+
+    Ok(detect_pitch(&downmix_channels(&channels), sample_rate).map(|frequency| {
+        let semitone = frequency_to_semitone(frequency);
+        let root_note = semitone.round();
+        (root_note as i32, 100f32 * (semitone - root_note))
+    }))
+}
+
+/// Sustain loop stored in the `smpl` chunk of a WAV file, if any.
+pub fn read_loop_points(src_file: &Path) -> Result<Option<(usize, usize)>, Box<dyn Error>> {
+    Ok(read_sample_info(src_file)?.and_then(|info| info.loop_points))
+}
+
+/// Repeats `data[loop_start..loop_end]` after the first pass until `target_len` is reached.
+fn sustain_loop(data: &[f32], loop_start: usize, loop_end: usize, target_len: usize) -> Vec<f32> {
+    let mut out = Vec::with_capacity(target_len);
+    out.extend_from_slice(&data[..loop_end.min(target_len)]);
+    while out.len() < target_len {
+        let remain = target_len - out.len();
+        out.extend_from_slice(&data[loop_start..loop_end.min(loop_start + remain)]);
+    }
+
+    out
+}
+
+/// Like `sustain_loop`, but the loop ends `crossfade` frames early and starts by fading
+/// from those frames into its own beginning, so that the seam does not click.
+fn crossfade_loop(data: &[f32], loop_start: usize, loop_end: usize, crossfade: usize, target_len: usize) -> Vec<f32> {
+    let crossfade = crossfade.min((loop_end - loop_start) / 2);
+    let seam = loop_end - crossfade;
+    let mut body = data[loop_start..seam].to_vec();
+    for (i, d) in body.iter_mut().take(crossfade).enumerate() {
+        let g = i as f32 / crossfade as f32;
+        *d = *d * g + data[seam + i] * (1f32 - g);
+    }
+
+    let mut out = Vec::with_capacity(target_len);
+    out.extend_from_slice(&data[..seam.min(target_len)]);
+    while out.len() < target_len {
+        let remain = target_len - out.len();
+        out.extend_from_slice(&body[..body.len().min(remain)]);
+    }
+
+    out
+}
+
+/// Plays up to `loop_end`, then back and forth between the loop's ends until `target_len` is reached.
+fn ping_pong_loop(data: &[f32], loop_start: usize, loop_end: usize, target_len: usize) -> Vec<f32> {
+    let forward = &data[loop_start..loop_end];
+    // Each turn skips the frame it turns at so that it is not played twice.
+    let cycle: Vec<f32> = forward.iter().rev().skip(1).chain(forward.iter().skip(1)).copied().collect();
+
+    let mut out = Vec::with_capacity(target_len);
+    out.extend_from_slice(&data[..loop_end.min(target_len)]);
+    while out.len() < target_len {
+        if cycle.is_empty() {
+            out.push(forward[0]);
         } else {
-            reader
-                .samples::<i32>()
-                .map(|s| s.unwrap_or(0) as f32 / i16::MAX as f32)
-                .collect()
+            let remain = target_len - out.len();
+            out.extend_from_slice(&cycle[..cycle.len().min(remain)]);
+        }
+    }
+
+    out
+}
+
+/// Recorded audio with the tuning and sustain loop used to play it at any pitch.
+#[derive(Clone)]
+pub struct Sample {
+    note: Note,
+    root: f32,
+    loop_points: Option<(usize, usize)>,
+    engine: StretchEngine,
+}
+
+impl Sample {
+    /// `root` is the semitone at which `channels` play unchanged. Invalid loop points are ignored.
+    pub fn new(channels: Vec<Vec<f32>>, sample_rate: u32, root: f32, loop_points: Option<(usize, usize)>) -> Self {
+        let note = Note::from_channels(channels, sample_rate, None);
+        let loop_points = loop_points.filter(|&(start, end)| start < end && end <= note.data().len());
+
+        Self {
+            note,
+            root,
+            loop_points,
+            engine: StretchEngine::Resample,
+        }
+    }
+
+    pub fn with_engine(self, engine: StretchEngine) -> Self {
+        Self {
+            engine,
+            ..self
+        }
+    }
+
+    pub fn note(&self) -> &Note {
+        &self.note
+    }
+
+    pub fn root(&self) -> f32 {
+        self.root
+    }
+
+    pub fn loop_points(&self) -> Option<(usize, usize)> {
+        self.loop_points
+    }
+
+    /// The frames from `start` to `end`, reversed if `reverse` is set. The loop moves with them.
+    pub fn trim(&self, start: usize, end: usize, reverse: bool) -> Sample {
+        let end = end.min(self.note.data().len());
+        let start = start.min(end);
+        let length = end - start;
+        let loop_points = self.loop_points
+            .map(|(loop_start, loop_end)| (loop_start.clamp(start, end) - start, loop_end.clamp(start, end) - start))
+            .map(|(loop_start, loop_end)| if reverse { (length - loop_end, length - loop_start) } else { (loop_start, loop_end) });
+        let channels = self.note.channels().iter()
+            .map(|data| if reverse {
+                data[start..end].iter().rev().copied().collect()
+            } else {
+                data[start..end].to_vec()
+            })
+            .collect();
+
+        Sample::new(channels, self.note.sample_rate(), self.root, loop_points).with_engine(self.engine)
+    }
+
+    /// The sample transposed relative to its root, with its loop points moved along.
+    fn transposed(&self, request: &NoteRequest) -> (Note, Option<(usize, usize)>) {
+        let factor = 2f32.powf((request.semitone() - self.root) / 12f32);
+        let channels = pitch_shift(self.note.channels(), factor, self.engine, self.note.sample_rate());
+        let transposed = Note::from_channels(channels, self.note.sample_rate(), self.note.envelope().clone());
+        // Only `Resample` changes the length, and the loop with it.
+        let scale = transposed.data().len() as f32 / self.note.data().len().max(1) as f32;
+        let loop_points = self.loop_points
+            .map(|(start, end)| ((start as f32 * scale) as usize, ((end as f32 * scale) as usize).min(transposed.data().len())))
+            .filter(|(start, end)| start < end);
+
+        (transposed, loop_points)
+    }
+
+    /// Transposes the sample relative to its root. With a sustain loop the result is
+    /// extended to the note's length, otherwise the transposed sample is returned as is.
+    pub fn transpose(&self, request: &NoteRequest) -> Note {
+        let (transposed, loop_points) = self.transposed(request);
+        match loop_points {
+            Some((start, end)) => {
+                let target_len = (request.seconds() * transposed.sample_rate() as f32) as usize;
+                transposed.map_channels(|data| sustain_loop(data, start, end, target_len))
+            },
+            None => transposed,
+        }
+    }
+
+    /// Transposes the sample relative to its root and plays it in `mode`. Loops repeat the
+    /// sustain loop, or the whole sample without one, up to the note's length.
+    pub fn play(&self, request: &NoteRequest, mode: PlaybackMode, crossfade_seconds: f32) -> Note {
+        let (transposed, loop_points) = self.transposed(request);
+        let length = transposed.data().len();
+        let Some((start, end)) = loop_points.or(Some((0, length))).filter(|(start, end)| start < end) else {
+            return transposed;
         };
+        let target_len = (request.seconds() * transposed.sample_rate() as f32) as usize;
+        let crossfade = (crossfade_seconds * transposed.sample_rate() as f32) as usize;
+        match mode {
+            PlaybackMode::OneShot => transposed,
+            PlaybackMode::Loop => transposed.map_channels(|data| crossfade_loop(data, start, end, crossfade, target_len)),
+            PlaybackMode::PingPong => transposed.map_channels(|data| ping_pong_loop(data, start, end, target_len)),
+            PlaybackMode::Stretch => {
+                let ratio = target_len as f32 / length as f32;
+                let channels = time_stretch(transposed.channels(), ratio, self.engine, transposed.sample_rate());
+                Note::from_channels(channels, transposed.sample_rate(), transposed.envelope().clone())
+            },
+        }
+    }
+}
+
+/// A sample of an instrument file played over MIDI key and velocity ranges.
+pub struct SampleRegion {
+    keys: (u8, u8),
+    velocities: (u8, u8),
+    sample: Sample,
+    gain: f32,
+    envelope: Option<Envelope>,
+}
+
+impl SampleRegion {
+    pub fn new(keys: (u8, u8), velocities: (u8, u8), sample: Sample, gain: f32, envelope: Option<Envelope>) -> Self {
+        Self {
+            keys,
+            velocities,
+            sample,
+            gain,
+            envelope,
+        }
+    }
+
+    pub fn keys(&self) -> (u8, u8) {
+        self.keys
+    }
+
+    pub fn sample(&self) -> &Sample {
+        &self.sample
+    }
+
+    /// Longest release among the envelopes of the regions.
+    pub fn release(regions: &[SampleRegion]) -> f32 {
+        regions.iter()
+            .filter_map(|region| region.envelope.as_ref())
+            .fold(0f32, |a, envelope| a.max(envelope.release()))
+    }
+
+    /// Sums every region covering the note's key and velocity, with the envelope of the first one.
+    /// Mono regions are spread over every channel of multi-channel ones.
+    pub fn render(regions: &[SampleRegion], request: &NoteRequest) -> Note {
+        let key = (request.semitone().round() as i32 + MIDI_NOTE_C4).clamp(0, MIDI_MAX as i32) as u8;
+        let velocity = (request.velocity() * MIDI_MAX).round().clamp(0f32, MIDI_MAX) as u8;
+        let regions: Vec<&SampleRegion> = regions.iter()
+            .filter(|region| region.keys.0 <= key && key <= region.keys.1)
+            .filter(|region| region.velocities.0 <= velocity && velocity <= region.velocities.1)
+            .collect();
+
+        let num_channels = regions.iter().map(|region| region.sample.note().channels().len()).max().unwrap_or(1);
+        let mut channels = vec![vec![0f32; request.length()]; num_channels];
+        for region in regions.iter() {
+            let note = region.sample.transpose(request);
+            for (channel, data) in channels.iter_mut().enumerate() {
+                let region_channel = &note.channels()[channel.min(note.channels().len() - 1)];
+                let region_data = resample_data(region_channel.clone(), note.sample_rate(), request.sample_rate());
+                for (d, r) in data.iter_mut().zip(region_data.iter()) {
+                    *d += region.gain * r;
+                }
+            }
+        }
+
+        Note::from_channels(channels, request.sample_rate(), regions.first().and_then(|region| region.envelope.clone()))
+    }
+}
+
+pub struct Sampler {
+    score: ScoreSampler,
+    sample: Sample,
+}
+
+impl Sampler {
+    pub fn new(score: ScoreSampler) -> Result<Self, Box<dyn Error>> {
+        let (channels, sample_rate) = read_wav(score.path())?;
+        let info = read_sample_info(score.path())?;
+
+        let root_note = score.root_note()
+            .or(info.as_ref().map(|info| info.root_note))
+            .unwrap_or(0);
+        let fine_tune = score.fine_tune()
+            .or(info.as_ref().map(|info| info.fine_tune))
+            .unwrap_or(0f32);
+        let info_loop = info.as_ref().and_then(|info| info.loop_points);
+        let loop_start = score.loop_start().or(info_loop.map(|(start, _)| start));
+        let loop_end = score.loop_end().or(info_loop.map(|(_, end)| end));
+        let loop_points = loop_start.zip(loop_end);
+
+        let sample = Sample::new(channels, sample_rate, root_note as f32 + fine_tune / 100f32, loop_points)
+            .with_engine(score.engine().unwrap_or(StretchEngine::Resample));
 
         Ok(Self {
-            src_file,
-            note: Note::new(data, spec.sample_rate, None),
+            score,
+            sample,
         })
     }
 
     pub fn src_file(&self) -> &PathBuf {
-        &self.src_file
+        self.score.path()
+    }
+
+    /// The note's playback, completed by the source's.
+    fn playback(&self, playback: Option<ScorePlayback>) -> ScorePlayback {
+        playback.unwrap_or_default().or(self.score.playback().unwrap_or_default())
     }
 }
 
 impl SoundSource for Sampler {
     fn get_base(&self) -> Note {
-        self.note.clone()
+        self.sample.note().clone()
     }
 
     fn to_score_part_source(&self) -> ScorePartSource {
-        ScorePartSource::Sampler(self.src_file.to_path_buf())
+        ScorePartSource::Sampler(self.score.clone())
+    }
+
+    fn is_one_shot(&self, playback: Option<ScorePlayback>) -> bool {
+        self.playback(playback).mode() == Some(PlaybackMode::OneShot)
+    }
+
+    /// Transposes the file relative to its root and plays it in the note's playback mode, or the source's.
+    /// Without a mode, only the sustain loop, if any, is repeated to fill the note.
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        let playback = self.playback(request.playback());
+        let sample = self.sample.trim(
+            playback.start().unwrap_or(0),
+            playback.end().unwrap_or(usize::MAX),
+            playback.reverse().unwrap_or(false),
+        );
+        let crossfade = playback.crossfade().unwrap_or(DEFAULT_CROSSFADE_SECONDS);
+
+        Some(match playback.mode() {
+            Some(PlaybackMode::OneShot) => sample.play(request, PlaybackMode::OneShot, crossfade),
+            Some(mode) => fit_length(sample.play(request, mode, crossfade), request.seconds()),
+            None => fit_length(sample.transpose(request), request.seconds()),
+        })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            chunk.push(0);
+        }
+
+        chunk
+    }
+
+    fn wav_with_smpl(unity_note: u32, pitch_fraction: u32, loop_points: (u32, u32)) -> Vec<u8> {
+        let mut fmt = vec![];
+        for value in [1u16, 1] {
+            fmt.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [44_100u32, 88_200] {
+            fmt.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [2u16, 16] {
+            fmt.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let mut smpl = vec![];
+        // Manufacturer, product, sample period, unity note, pitch fraction, SMPTE format and offset,
+        // loop count and sampler data, then one loop of id, type, start, end, fraction and play count.
+        for value in [0, 0, 22_675, unity_note, pitch_fraction, 0, 0, 1, 0, 0, 0, loop_points.0, loop_points.1, 0, 0] {
+            smpl.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let mut body = b"WAVE".to_vec();
+        body.extend(chunk(b"fmt ", &fmt));
+        body.extend(chunk(b"smpl", &smpl));
+        body.extend(chunk(b"data", &[0u8; 200]));
+
+        chunk(b"RIFF", &body)
+    }
+
+    #[test]
+    fn parses_smpl_root_and_loop() {
+        let wav = wav_with_smpl(69, u32::MAX / 2 + 1, (10, 89));
+        let info = parse_sample_info(&wav).unwrap().unwrap();
+
+        assert_eq!(info.root_note, 9);
+        assert!((info.fine_tune - 50f32).abs() < 1e-3);
+        assert_eq!(info.loop_points, Some((10, 90)));
+    }
+
+    #[test]
+    fn wav_without_smpl_has_no_info() {
+        let mut body = b"WAVE".to_vec();
+        body.extend(chunk(b"data", &[0u8; 8]));
+
+        assert!(parse_sample_info(&chunk(b"RIFF", &body)).unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated_smpl_chunk_is_an_error() {
+        let wav = wav_with_smpl(60, 0, (0, 10));
+        let smpl = wav.windows(4).position(|id| id == b"smpl").unwrap();
+
+        assert!(parse_sample_info(&wav[..smpl + 40]).is_err());
+    }
+
+    #[test]
+    fn detects_root_of_a4() {
+        let path = std::env::temp_dir().join("sound_sampler_detect_root_a4.wav");
+        let sine: Vec<f32> = (0..44_100)
+            .map(|n| (2f32 * std::f32::consts::PI * 440f32 * n as f32 / 44_100f32).sin() * 0.5f32)
+            .collect();
+        crate::utils::write_wav(&path, &[sine], 44_100).unwrap();
+        let root = detect_root(&path);
+        fs::remove_file(&path).unwrap();
+
+        let (root_note, fine_tune) = root.unwrap().unwrap();
+        assert_eq!(root_note, 9);
+        assert!(fine_tune.abs() < 5f32);
+    }
+}
\ No newline at end of file
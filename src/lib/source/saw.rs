@@ -0,0 +1,29 @@
+use crate::note::Note;
+use crate::score::ScorePartSource;
+use crate::source::{oscillate, poly_blep, NoteRequest, SoundSource};
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+pub fn saw_wave(phase: f32, dt: f32) -> f32 {
+    2f32 * phase - 1f32 - poly_blep(phase, dt)
+}
+
+pub struct Saw;
+
+impl SoundSource for Saw {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        Note::new(oscillate(&request, saw_wave), DEFAULT_SAMPLE_RATE, None)
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::Saw
+    }
+
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        Some(Note::new(oscillate(request, saw_wave), request.sample_rate(), None))
+    }
+}
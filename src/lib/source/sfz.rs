@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::envelope::Envelope;
+use crate::error::SourceError;
+use crate::note::Note;
+use crate::score::ScorePartSource;
+use crate::source::{MIDI_NOTE_C4, NoteRequest, SoundSource};
+use crate::source::sampler::{read_loop_points, Sample, SampleRegion};
+use crate::utils::read_wav;
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+const MIDI_MAX: u8 = 127;
+const REGION_LEVEL: usize = 3;
+const AMPEG_OPCODES: [&str; 4] = ["ampeg_attack", "ampeg_decay", "ampeg_sustain", "ampeg_release"];
+
+type Opcodes = HashMap<String, String>;
+
+fn strip_comments(text: &str) -> String {
+    text.lines()
+        .map(|line| match line.find("//") {
+            Some(index) => &line[..index],
+            None => line,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Opcodes of the text between two headers. Values run until the next opcode,
+/// so sample paths may contain spaces.
+fn parse_opcodes(text: &str, opcodes: &mut Opcodes) {
+    let key_start = |eq: usize| text[..eq].rfind(char::is_whitespace).map_or(0, |index| index + 1);
+    let equals: Vec<usize> = text.match_indices('=').map(|(index, _)| index).collect();
+    for (i, &eq) in equals.iter().enumerate() {
+        let value_end = equals.get(i + 1).map_or(text.len(), |&next| key_start(next));
+        let key = text[key_start(eq)..eq].trim();
+        let value = text[eq + 1..value_end.max(eq + 1)].trim();
+        if !key.is_empty() {
+            opcodes.insert(key.to_string(), value.to_string());
+        }
+    }
+}
+
+/// Opcodes of every `<region>`, inheriting from the enclosing `<global>`, `<master>` and `<group>`.
+/// A header without its closing `>` is an error.
+fn parse_regions(text: &str) -> Result<(Opcodes, Vec<Opcodes>), Box<dyn Error>> {
+    let text = strip_comments(text);
+    let mut control = Opcodes::new();
+    let mut levels: [Opcodes; REGION_LEVEL] = Default::default();
+    let mut regions: Vec<Opcodes> = vec![];
+    let mut current: Option<usize> = None;
+
+    let mut rest = text.as_str();
+    loop {
+        let (body, next) = match rest.find('<') {
+            Some(index) => (&rest[..index], Some(index)),
+            None => (rest, None),
+        };
+        match current {
+            Some(level) if level < levels.len() => parse_opcodes(body, &mut levels[level]),
+            Some(REGION_LEVEL) => if let Some(region) = regions.last_mut() {
+                parse_opcodes(body, region)
+            },
+            Some(_) => {},
+            None => parse_opcodes(body, &mut control),
+        }
+
+        let Some(index) = next else { break };
+        let Some(end) = rest[index..].find('>') else {
+            return Err(Box::new(SourceError::new(format!("unterminated header {}", rest[index..].trim_end()))));
+        };
+        let header = &rest[index + 1..index + end];
+        rest = &rest[index + end + 1..];
+        current = match header {
+            "control" => None,
+            "global" => Some(0),
+            "master" => Some(1),
+            "group" => Some(2),
+            "region" => Some(REGION_LEVEL),
+            _ => Some(usize::MAX),
+        };
+        if let Some(level) = current {
+            // Starting a level clears the opcodes of the levels inside it.
+            for inner in levels.iter_mut().skip(level.min(REGION_LEVEL)) {
+                inner.clear();
+            }
+            if level == REGION_LEVEL {
+                let mut region = Opcodes::new();
+                for opcodes in levels.iter() {
+                    region.extend(opcodes.iter().map(|(key, value)| (key.clone(), value.clone())));
+                }
+                regions.push(region);
+            }
+        }
+    }
+
+    Ok((control, regions))
+}
+
+/// MIDI note number of a number or a note name such as `c4` or `f#3`.
+fn parse_key(value: &str) -> Option<i32> {
+    if let Ok(key) = value.parse::<i32>() {
+        return Some(key);
+    }
+    let value = value.to_lowercase();
+    let mut chars = value.chars();
+    let pitch_class = match chars.next()? {
+        'c' => 0,
+        'd' => 2,
+        'e' => 4,
+        'f' => 5,
+        'g' => 7,
+        'a' => 9,
+        'b' => 11,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    let (accidental, octave) = match rest.chars().next()? {
+        '#' => (1, &rest[1..]),
+        'b' if rest.len() > 1 => (-1, &rest[1..]),
+        _ => (0, rest),
+    };
+
+    Some((octave.parse::<i32>().ok()? + 1) * 12 + pitch_class + accidental)
+}
+
+fn midi_value(opcodes: &Opcodes, name: &str) -> Option<u8> {
+    opcodes.get(name).and_then(|value| parse_key(value)).map(|key| key.clamp(0, MIDI_MAX as i32) as u8)
+}
+
+fn float_value(opcodes: &Opcodes, name: &str) -> Option<f32> {
+    opcodes.get(name).and_then(|value| value.parse::<f32>().ok())
+}
+
+struct SampleFile {
+    channels: Vec<Vec<f32>>,
+    sample_rate: u32,
+    loop_points: Option<(usize, usize)>,
+}
+
+pub struct Sfz {
+    path: PathBuf,
+    regions: Vec<SampleRegion>,
+}
+
+impl Sfz {
+    pub fn new(path: PathBuf) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(&path)?;
+        let (control, regions) = parse_regions(&text)?;
+        let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let default_path = control.get("default_path").map(|value| value.replace('\\', "/")).unwrap_or_default();
+
+        let mut files: HashMap<PathBuf, SampleFile> = HashMap::new();
+        let mut sample_regions = vec![];
+        for region in regions {
+            let Some(sample) = region.get("sample") else { continue };
+            let sample_path = directory.join(&default_path).join(sample.replace('\\', "/"));
+            if !files.contains_key(&sample_path) {
+                let (channels, sample_rate) = read_wav(&sample_path)?;
+                let loop_points = read_loop_points(&sample_path)?;
+                files.insert(sample_path.clone(), SampleFile { channels, sample_rate, loop_points });
+            }
+            sample_regions.push(Self::create_region(&region, &files[&sample_path]));
+        }
+        if sample_regions.is_empty() {
+            return Err(Box::new(SourceError::new(format!("{} has no regions with a sample", path.display()))));
+        }
+
+        Ok(Self {
+            path,
+            regions: sample_regions,
+        })
+    }
+
+    fn create_region(region: &Opcodes, file: &SampleFile) -> SampleRegion {
+        let length = file.channels[0].len();
+        let key = midi_value(region, "key");
+        let keys = (
+            midi_value(region, "lokey").or(key).unwrap_or(0),
+            midi_value(region, "hikey").or(key).unwrap_or(MIDI_MAX),
+        );
+        let velocities = (
+            midi_value(region, "lovel").unwrap_or(0),
+            midi_value(region, "hivel").unwrap_or(MIDI_MAX),
+        );
+
+        let offset = (float_value(region, "offset").unwrap_or(0f32) as usize).min(length);
+        let end = float_value(region, "end").map_or(length, |end| (end as usize + 1).min(length)).max(offset);
+        let key_center = midi_value(region, "pitch_keycenter").or(key).map_or(MIDI_NOTE_C4, |key| key as i32);
+        let root = (key_center - MIDI_NOTE_C4) as f32
+            - float_value(region, "transpose").unwrap_or(0f32)
+            - float_value(region, "tune").unwrap_or(0f32) / 100f32;
+
+        let loop_start = float_value(region, "loop_start").or(float_value(region, "loopstart")).map(|start| start as usize);
+        // Loop ends are inclusive.
+        let loop_end = float_value(region, "loop_end").or(float_value(region, "loopend")).map(|end| end as usize + 1);
+        let region_loop = loop_start.zip(loop_end);
+        let loop_points = match region.get("loop_mode").map(String::as_str) {
+            Some("loop_continuous") | Some("loop_sustain") => region_loop.or(file.loop_points),
+            // Regions loop by default when the file has a loop.
+            None => file.loop_points.map(|file_loop| region_loop.unwrap_or(file_loop)),
+            _ => None,
+        }.map(|(start, end)| (start.saturating_sub(offset), end.saturating_sub(offset)));
+
+        let envelope = if AMPEG_OPCODES.iter().any(|opcode| region.contains_key(*opcode)) {
+            Some(Envelope::new(
+                float_value(region, "ampeg_attack").unwrap_or(0f32),
+                float_value(region, "ampeg_decay").unwrap_or(0f32),
+                float_value(region, "ampeg_sustain").unwrap_or(100f32) / 100f32,
+                float_value(region, "ampeg_release").unwrap_or(0f32),
+            ))
+        } else {
+            None
+        };
+
+        SampleRegion::new(
+            keys,
+            velocities,
+            Sample::new(
+                file.channels.iter().map(|data| data[offset..end].to_vec()).collect(),
+                file.sample_rate,
+                root,
+                loop_points,
+            ),
+            10f32.powf(float_value(region, "volume").unwrap_or(0f32) / 20f32),
+            envelope,
+        )
+    }
+}
+
+impl SoundSource for Sfz {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        self.render_note(&request).unwrap_or_else(|| self.get_rest(DEFAULT_SAMPLE_RATE))
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::Sfz(self.path.clone())
+    }
+
+    fn release(&self) -> f32 {
+        SampleRegion::release(&self.regions)
+    }
+
+    /// Sums every region covering the note's key and velocity.
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        Some(SampleRegion::render(&self.regions, request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SFZ: &str = "<control> default_path=samples\\
+<group> lokey=c3 hikey=b3 pitch_keycenter=60 loop_mode=loop_continuous // shared by both regions
+<region> sample=piano c3.wav loop_start=10 loop_end=89
+<region> sample=piano f3.wav pitch_keycenter=f3 tune=-50 lokey=f3
+";
+
+    fn sample_file() -> SampleFile {
+        SampleFile {
+            channels: vec![vec![0f32; 100]],
+            sample_rate: 44_100,
+            loop_points: Some((5, 50)),
+        }
+    }
+
+    #[test]
+    fn regions_inherit_group_opcodes() {
+        let (control, regions) = parse_regions(SFZ).unwrap();
+
+        assert_eq!(control["default_path"], "samples\\");
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0]["sample"], "piano c3.wav");
+        assert_eq!(regions[1]["hikey"], "b3");
+
+        let first = Sfz::create_region(&regions[0], &sample_file());
+        assert_eq!(first.keys(), (48, 59));
+        assert_eq!(first.sample().root(), 0f32);
+        assert_eq!(first.sample().loop_points(), Some((10, 90)));
+
+        let second = Sfz::create_region(&regions[1], &sample_file());
+        assert_eq!(second.keys(), (53, 59));
+        assert_eq!(second.sample().root(), -6.5f32);
+        // Without its own loop points the region loops the file's.
+        assert_eq!(second.sample().loop_points(), Some((5, 50)));
+    }
+
+    #[test]
+    fn unterminated_header_is_an_error() {
+        assert!(parse_regions("<group> lokey=c3\n<regi").is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use std::error::Error;
+
+use crate::analysis::{detect_slices, DEFAULT_SENSITIVITY};
+use crate::error::SourceError;
+use crate::note::Note;
+use crate::score::{ScorePartSource, ScoreSlicedSampler};
+use crate::source::{NoteRequest, SoundSource};
+use crate::utils::{downmix_channels, read_wav, resample_data};
+
+
+const SLICE_FADE_SECONDS: f32 = 0.002f32;
+
+/// First frame of each slice of the file, sorted and inside it.
+pub fn slice_starts(score: &ScoreSlicedSampler, channels: &[Vec<f32>], sample_rate: u32) -> Vec<usize> {
+    let length = channels[0].len();
+    let mut starts = match score.slices() {
+        Some(slices) => slices.clone(),
+        None => detect_slices(&downmix_channels(channels), sample_rate, score.sensitivity().unwrap_or(DEFAULT_SENSITIVITY)),
+    };
+    starts.retain(|&start| start < length);
+    starts.sort_unstable();
+    starts.dedup();
+
+    starts
+}
+
+pub struct SlicedSampler {
+    score: ScoreSlicedSampler,
+    note: Note,
+    slices: Vec<Note>,
+}
+
+impl SlicedSampler {
+    pub fn new(score: ScoreSlicedSampler) -> Result<Self, Box<dyn Error>> {
+        let (channels, sample_rate) = read_wav(score.path())?;
+        let starts = slice_starts(&score, &channels, sample_rate);
+        if starts.is_empty() {
+            return Err(Box::new(SourceError::new(format!("{} has no slices", score.path().display()))));
+        }
+
+        let length = channels[0].len();
+        let fade = (SLICE_FADE_SECONDS * sample_rate as f32) as usize;
+        let slices = starts.iter().enumerate().map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(length);
+            let slice: Vec<Vec<f32>> = channels.iter().map(|data| {
+                let mut slice = data[start..end].to_vec();
+                // Cutting before the next transient would otherwise click.
+                let fade = fade.min(slice.len());
+                let slice_len = slice.len();
+                for (n, d) in slice.iter_mut().skip(slice_len - fade).enumerate() {
+                    *d *= 1f32 - (n + 1) as f32 / fade as f32;
+                }
+                slice
+            }).collect();
+            Note::from_channels(slice, sample_rate, None)
+        }).collect();
+
+        Ok(Self {
+            score,
+            note: Note::from_channels(channels, sample_rate, None),
+            slices,
+        })
+    }
+
+    pub fn slices(&self) -> &Vec<Note> {
+        &self.slices
+    }
+}
+
+impl SoundSource for SlicedSampler {
+    fn get_base(&self) -> Note {
+        self.note.clone()
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::SlicedSampler(self.score.clone())
+    }
+
+    /// Plays the slice of the note's semitone at its recorded pitch, cut at the end of the note.
+    /// Semitones without a slice are silent.
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        let index = request.semitone().round() as i32 - self.score.first_note().unwrap_or(0);
+        let channels = match usize::try_from(index).ok().and_then(|index| self.slices.get(index)) {
+            Some(slice) => slice.channels().iter()
+                .map(|data| {
+                    let mut data = resample_data(data.clone(), slice.sample_rate(), request.sample_rate());
+                    data.resize(request.length(), 0f32);
+                    data
+                })
+                .collect(),
+            None => vec![vec![0f32; request.length()]],
+        };
+
+        Some(Note::from_channels(channels, request.sample_rate(), None))
+    }
+}
@@ -0,0 +1,461 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::envelope::Envelope;
+use crate::error::SourceError;
+use crate::note::Note;
+use crate::score::ScorePartSource;
+use crate::source::{MIDI_NOTE_C4, NoteRequest, SoundSource};
+use crate::source::sampler::{Sample, SampleRegion};
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+const MIDI_MAX: u8 = 127;
+
+const PHDR_SIZE: usize = 38;
+const INST_SIZE: usize = 22;
+const BAG_SIZE: usize = 4;
+const GEN_SIZE: usize = 4;
+const SHDR_SIZE: usize = 46;
+
+const GEN_START_OFFSET: u16 = 0;
+const GEN_END_OFFSET: u16 = 1;
+const GEN_LOOP_START_OFFSET: u16 = 2;
+const GEN_LOOP_END_OFFSET: u16 = 3;
+const GEN_START_COARSE_OFFSET: u16 = 4;
+const GEN_END_COARSE_OFFSET: u16 = 12;
+const GEN_ATTACK: u16 = 34;
+const GEN_DECAY: u16 = 36;
+const GEN_SUSTAIN: u16 = 37;
+const GEN_RELEASE: u16 = 38;
+const GEN_INSTRUMENT: u16 = 41;
+const GEN_KEY_RANGE: u16 = 43;
+const GEN_VELOCITY_RANGE: u16 = 44;
+const GEN_LOOP_START_COARSE_OFFSET: u16 = 45;
+const GEN_ATTENUATION: u16 = 48;
+const GEN_LOOP_END_COARSE_OFFSET: u16 = 50;
+const GEN_COARSE_TUNE: u16 = 51;
+const GEN_FINE_TUNE: u16 = 52;
+const GEN_SAMPLE_ID: u16 = 53;
+const GEN_SAMPLE_MODES: u16 = 54;
+const GEN_ROOT_KEY: u16 = 58;
+
+// Default of the envelope time generators, about 1ms.
+const DEFAULT_TIMECENTS: i16 = -12_000;
+const COARSE_OFFSET_UNIT: i32 = 32_768;
+const NO_ORIGINAL_PITCH: u8 = 255;
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn format_error(path: &Path, message: &str) -> Box<dyn Error> {
+    Box::new(SourceError::new(format!("{} is not a valid SoundFont: {}", path.display(), message)))
+}
+
+/// Sub-chunks of a RIFF list body, keyed by their id. None if a chunk runs past the end of the body.
+fn read_chunks(data: &[u8]) -> Option<Vec<([u8; 4], &[u8])>> {
+    let mut chunks = vec![];
+    let mut offset = 0;
+    while let Some(size) = read_u32(data, offset + 4) {
+        let start = offset + 8;
+        let end = start.checked_add(size as usize)?;
+        let id = [data[offset], data[offset + 1], data[offset + 2], data[offset + 3]];
+        chunks.push((id, data.get(start..end)?));
+        // Chunks are padded to an even size.
+        offset = end + (size as usize & 1);
+    }
+
+    Some(chunks)
+}
+
+fn find_chunk<'a>(chunks: &[([u8; 4], &'a [u8])], id: &[u8; 4]) -> Option<&'a [u8]> {
+    chunks.iter().find(|(chunk_id, _)| chunk_id == id).map(|(_, data)| *data)
+}
+
+/// Body of the `LIST` chunk of the given type.
+fn find_list<'a>(chunks: &[([u8; 4], &'a [u8])], list_type: &[u8; 4]) -> Option<&'a [u8]> {
+    chunks.iter()
+        .find(|(id, data)| id == b"LIST" && data.get(0..4) == Some(list_type))
+        .map(|(_, data)| &data[4..])
+}
+
+/// Generators of one preset or instrument zone.
+#[derive(Clone, Default)]
+struct Generators {
+    values: Vec<(u16, i16)>,
+}
+
+impl Generators {
+    fn get(&self, operator: u16) -> Option<i16> {
+        self.values.iter().rev().find(|(op, _)| *op == operator).map(|(_, amount)| *amount)
+    }
+
+    fn range(&self, operator: u16) -> Option<(u8, u8)> {
+        self.get(operator).map(|amount| {
+            let [low, high] = amount.to_le_bytes();
+            (low, high)
+        })
+    }
+
+    fn index(&self, operator: u16) -> Option<usize> {
+        self.get(operator).map(|amount| amount as u16 as usize)
+    }
+
+    /// Zone values override the global zone's.
+    fn merged(global: &Generators, local: &Generators) -> Generators {
+        Generators {
+            values: global.values.iter().chain(local.values.iter()).copied().collect(),
+        }
+    }
+}
+
+/// Generators of each zone of the header at `index`, given the bag index of each header.
+fn zone_generators(bag_indices: &[usize], index: usize, bags: &[u8], generators: &[u8]) -> Vec<Generators> {
+    let bag_count = bags.len() / BAG_SIZE;
+    let generator_count = generators.len() / GEN_SIZE;
+    let first_bag = bag_indices[index].min(bag_count);
+    let last_bag = bag_indices.get(index + 1).copied().unwrap_or(bag_count).min(bag_count);
+
+    (first_bag..last_bag).map(|bag| {
+        let first = read_u16(bags, bag * BAG_SIZE).unwrap_or(0) as usize;
+        let last = read_u16(bags, (bag + 1) * BAG_SIZE).map_or(generator_count, |index| index as usize);
+        Generators {
+            values: (first..last.min(generator_count)).filter_map(|generator| {
+                let offset = generator * GEN_SIZE;
+                Some((read_u16(generators, offset)?, read_u16(generators, offset + 2)? as i16))
+            }).collect(),
+        }
+    }).collect()
+}
+
+/// Splits zones into the global zone, which lacks `terminal`, and the others.
+fn split_global(mut zones: Vec<Generators>, terminal: u16) -> (Generators, Vec<Generators>) {
+    match zones.first() {
+        Some(first) if first.get(terminal).is_none() => {
+            let global = zones.remove(0);
+            (global, zones.into_iter().filter(|zone| zone.get(terminal).is_some()).collect())
+        },
+        _ => (Generators::default(), zones.into_iter().filter(|zone| zone.get(terminal).is_some()).collect()),
+    }
+}
+
+fn intersect((low_a, high_a): (u8, u8), (low_b, high_b): (u8, u8)) -> Option<(u8, u8)> {
+    let range = (low_a.max(low_b), high_a.min(high_b));
+    if range.0 <= range.1 { Some(range) } else { None }
+}
+
+fn timecents_to_seconds(timecents: i32) -> f32 {
+    2f32.powf(timecents as f32 / 1200f32)
+}
+
+fn centibels_to_gain(centibels: i32) -> f32 {
+    10f32.powf(-centibels.max(0) as f32 / 200f32)
+}
+
+pub struct SoundFont {
+    path: PathBuf,
+    bank: u16,
+    preset: u16,
+    zones: Vec<SampleRegion>,
+}
+
+impl SoundFont {
+    pub fn new(path: PathBuf, bank: u16, preset: u16) -> Result<Self, Box<dyn Error>> {
+        let file = fs::read(&path)?;
+        let zones = Self::parse_zones(&path, &file, bank, preset)?;
+
+        Ok(Self {
+            path,
+            bank,
+            preset,
+            zones,
+        })
+    }
+
+    /// Zones of the preset in the bytes of the SoundFont at `path`.
+    fn parse_zones(path: &Path, file: &[u8], bank: u16, preset: u16) -> Result<Vec<SampleRegion>, Box<dyn Error>> {
+        if file.get(0..4) != Some(b"RIFF") || file.get(8..12) != Some(b"sfbk") {
+            return Err(format_error(path, "missing sfbk header"));
+        }
+        let truncated = || format_error(path, "truncated chunk");
+        let chunks = read_chunks(&file[12..]).ok_or_else(truncated)?;
+        let sdta = read_chunks(find_list(&chunks, b"sdta").ok_or_else(|| format_error(path, "missing sdta list"))?)
+            .ok_or_else(truncated)?;
+        let pdta = read_chunks(find_list(&chunks, b"pdta").ok_or_else(|| format_error(path, "missing pdta list"))?)
+            .ok_or_else(truncated)?;
+        let sample_data: Vec<f32> = find_chunk(&sdta, b"smpl")
+            .ok_or_else(|| format_error(path, "missing smpl chunk"))?
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / i16::MAX as f32)
+            .collect();
+        let pdta_chunk = |id: &[u8; 4]| find_chunk(&pdta, id)
+            .ok_or_else(|| format_error(path, &format!("missing {} chunk", String::from_utf8_lossy(id))));
+        let phdr = pdta_chunk(b"phdr")?;
+        let pbag = pdta_chunk(b"pbag")?;
+        let pgen = pdta_chunk(b"pgen")?;
+        let inst = pdta_chunk(b"inst")?;
+        let ibag = pdta_chunk(b"ibag")?;
+        let igen = pdta_chunk(b"igen")?;
+        let shdr = pdta_chunk(b"shdr")?;
+
+        let preset_bags: Vec<usize> = phdr.chunks_exact(PHDR_SIZE)
+            .map(|header| read_u16(header, 24).unwrap_or(0) as usize)
+            .collect();
+        // The last preset header is the terminal record.
+        let preset_index = phdr.chunks_exact(PHDR_SIZE)
+            .take(preset_bags.len().saturating_sub(1))
+            .position(|header| read_u16(header, 20) == Some(preset) && read_u16(header, 22) == Some(bank))
+            .ok_or_else(|| Box::new(SourceError::new(format!(
+                "{} has no preset {} in bank {}", path.display(), preset, bank
+            ))) as Box<dyn Error>)?;
+        let instrument_bags: Vec<usize> = inst.chunks_exact(INST_SIZE)
+            .map(|header| read_u16(header, 20).unwrap_or(0) as usize)
+            .collect();
+        let sample_headers: Vec<&[u8]> = shdr.chunks_exact(SHDR_SIZE).collect();
+
+        let mut zones = vec![];
+        let (preset_global, preset_zones) = split_global(
+            zone_generators(&preset_bags, preset_index, pbag, pgen), GEN_INSTRUMENT
+        );
+        for preset_zone in preset_zones {
+            let preset_zone = Generators::merged(&preset_global, &preset_zone);
+            let instrument_index = match preset_zone.index(GEN_INSTRUMENT) {
+                Some(index) if index + 1 < instrument_bags.len() => index,
+                _ => continue,
+            };
+            let (instrument_global, instrument_zones) = split_global(
+                zone_generators(&instrument_bags, instrument_index, ibag, igen), GEN_SAMPLE_ID
+            );
+            for instrument_zone in instrument_zones {
+                let zone = Generators::merged(&instrument_global, &instrument_zone);
+                if let Some(zone) = Self::create_zone(&zone, &preset_zone, &sample_headers, &sample_data) {
+                    zones.push(zone);
+                }
+            }
+        }
+        if zones.is_empty() {
+            return Err(Box::new(SourceError::new(format!(
+                "preset {} in bank {} of {} has no playable zones", preset, bank, path.display()
+            ))));
+        }
+
+        Ok(zones)
+    }
+
+    fn create_zone(zone: &Generators, preset_zone: &Generators, sample_headers: &[&[u8]], sample_data: &[f32]) -> Option<SampleRegion> {
+        // Preset generators are added to the instrument's.
+        let sum = |operator: u16, default: i16| {
+            zone.get(operator).unwrap_or(default) as i32 + preset_zone.get(operator).unwrap_or(0) as i32
+        };
+        let full_range = (0u8, MIDI_MAX);
+        let keys = intersect(
+            zone.range(GEN_KEY_RANGE).unwrap_or(full_range),
+            preset_zone.range(GEN_KEY_RANGE).unwrap_or(full_range),
+        )?;
+        let velocities = intersect(
+            zone.range(GEN_VELOCITY_RANGE).unwrap_or(full_range),
+            preset_zone.range(GEN_VELOCITY_RANGE).unwrap_or(full_range),
+        )?;
+
+        // The last sample header is the terminal record.
+        let sample_index = zone.index(GEN_SAMPLE_ID).filter(|&index| index + 1 < sample_headers.len())?;
+        let header = sample_headers[sample_index];
+        let offset = |fine: u16, coarse: u16| {
+            zone.get(fine).unwrap_or(0) as i32 + zone.get(coarse).unwrap_or(0) as i32 * COARSE_OFFSET_UNIT
+        };
+        let position = |header_offset: usize, fine: u16, coarse: u16| -> Option<usize> {
+            let value = read_u32(header, header_offset)? as i64 + offset(fine, coarse) as i64;
+            Some(value.clamp(0, sample_data.len() as i64) as usize)
+        };
+        let start = position(20, GEN_START_OFFSET, GEN_START_COARSE_OFFSET)?;
+        let end = position(24, GEN_END_OFFSET, GEN_END_COARSE_OFFSET)?;
+        let loop_start = position(28, GEN_LOOP_START_OFFSET, GEN_LOOP_START_COARSE_OFFSET)?;
+        let loop_end = position(32, GEN_LOOP_END_OFFSET, GEN_LOOP_END_COARSE_OFFSET)?;
+        let sample_rate = read_u32(header, 36)?;
+        if start >= end || sample_rate == 0 {
+            return None;
+        }
+
+        let original_pitch = match *header.get(40)? {
+            NO_ORIGINAL_PITCH => MIDI_NOTE_C4,
+            pitch => pitch as i32,
+        };
+        let pitch_correction = *header.get(41)? as i8 as i32;
+        let root_key = match zone.get(GEN_ROOT_KEY) {
+            Some(key) if key >= 0 => key as i32,
+            _ => original_pitch,
+        };
+        let root = (root_key - MIDI_NOTE_C4 - sum(GEN_COARSE_TUNE, 0)) as f32
+            - (sum(GEN_FINE_TUNE, 0) + pitch_correction) as f32 / 100f32;
+
+        // Sample modes 1 and 3 loop while the note is held.
+        let loop_points = match zone.get(GEN_SAMPLE_MODES).unwrap_or(0) & 1 {
+            1 if start <= loop_start && loop_end <= end => Some((loop_start - start, loop_end - start)),
+            _ => None,
+        };
+
+        let envelope = Envelope::new(
+            timecents_to_seconds(sum(GEN_ATTACK, DEFAULT_TIMECENTS)),
+            timecents_to_seconds(sum(GEN_DECAY, DEFAULT_TIMECENTS)),
+            centibels_to_gain(sum(GEN_SUSTAIN, 0)),
+            timecents_to_seconds(sum(GEN_RELEASE, DEFAULT_TIMECENTS)),
+        );
+
+        Some(SampleRegion::new(
+            keys,
+            velocities,
+            Sample::new(vec![sample_data[start..end].to_vec()], sample_rate, root, loop_points),
+            centibels_to_gain(sum(GEN_ATTENUATION, 0)),
+            Some(envelope),
+        ))
+    }
+}
+
+impl SoundSource for SoundFont {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        self.render_note(&request).unwrap_or_else(|| self.get_rest(DEFAULT_SAMPLE_RATE))
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::SoundFont {
+            path: self.path.clone(),
+            bank: self.bank,
+            preset: self.preset,
+        }
+    }
+
+    fn release(&self) -> f32 {
+        SampleRegion::release(&self.zones)
+    }
+
+    /// Sums every zone covering the note's key and velocity, with the volume envelope of the first one.
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        Some(SampleRegion::render(&self.zones, request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            chunk.push(0);
+        }
+
+        chunk
+    }
+
+    fn list(list_type: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = list_type.to_vec();
+        for sub_chunk in chunks {
+            body.extend_from_slice(sub_chunk);
+        }
+
+        chunk(b"LIST", &body)
+    }
+
+    /// A record of `size` bytes starting with a name, with little endian values at the given offsets.
+    fn record(size: usize, values: &[(usize, &[u8])]) -> Vec<u8> {
+        let mut record = vec![0u8; size];
+        record[..4].copy_from_slice(b"name");
+        for (offset, bytes) in values {
+            record[*offset..*offset + bytes.len()].copy_from_slice(bytes);
+        }
+
+        record
+    }
+
+    fn generators(values: &[(u16, [u8; 2])]) -> Vec<u8> {
+        values.iter().flat_map(|(operator, amount)| {
+            let mut generator = operator.to_le_bytes().to_vec();
+            generator.extend_from_slice(amount);
+            generator
+        }).collect()
+    }
+
+    /// Preset 0 of bank 0 with one instrument zone over keys 48 to 72, rooted at key 62,
+    /// looping frames 20 to 80 of a 100 frame sample.
+    fn sound_font() -> Vec<u8> {
+        let samples: Vec<u8> = (0..100i16).flat_map(|i| (i * 100).to_le_bytes()).collect();
+        let phdr = [
+            record(PHDR_SIZE, &[(20, &0u16.to_le_bytes()), (22, &0u16.to_le_bytes()), (24, &0u16.to_le_bytes())]),
+            record(PHDR_SIZE, &[(24, &1u16.to_le_bytes())]),
+        ].concat();
+        let bags = |count: u16| [0u16.to_le_bytes(), 0u16.to_le_bytes(), count.to_le_bytes(), 0u16.to_le_bytes()].concat();
+        let pgen = generators(&[(GEN_INSTRUMENT, [0, 0]), (0, [0, 0])]);
+        let inst = [
+            record(INST_SIZE, &[(20, &0u16.to_le_bytes())]),
+            record(INST_SIZE, &[(20, &1u16.to_le_bytes())]),
+        ].concat();
+        let igen = generators(&[
+            (GEN_KEY_RANGE, [48, 72]),
+            (GEN_ROOT_KEY, 62i16.to_le_bytes()),
+            (GEN_SAMPLE_MODES, 1i16.to_le_bytes()),
+            (GEN_SAMPLE_ID, 0i16.to_le_bytes()),
+            (0, [0, 0]),
+        ]);
+        let shdr = [
+            record(SHDR_SIZE, &[
+                (20, &0u32.to_le_bytes()),
+                (24, &100u32.to_le_bytes()),
+                (28, &20u32.to_le_bytes()),
+                (32, &80u32.to_le_bytes()),
+                (36, &44_100u32.to_le_bytes()),
+                (40, &[60, 0]),
+            ]),
+            record(SHDR_SIZE, &[]),
+        ].concat();
+
+        let mut body = b"sfbk".to_vec();
+        body.extend(list(b"sdta", &[chunk(b"smpl", &samples)]));
+        body.extend(list(b"pdta", &[
+            chunk(b"phdr", &phdr),
+            chunk(b"pbag", &bags(1)),
+            chunk(b"pmod", &[0u8; 10]),
+            chunk(b"pgen", &pgen),
+            chunk(b"inst", &inst),
+            chunk(b"ibag", &bags(4)),
+            chunk(b"imod", &[0u8; 10]),
+            chunk(b"igen", &igen),
+            chunk(b"shdr", &shdr),
+        ]));
+
+        chunk(b"RIFF", &body)
+    }
+
+    #[test]
+    fn parses_zone_keys_root_and_loop() {
+        let zones = SoundFont::parse_zones(Path::new("test.sf2"), &sound_font(), 0, 0).unwrap();
+
+        assert_eq!(zones.len(), 1);
+        assert_eq!(zones[0].keys(), (48, 72));
+        assert_eq!(zones[0].sample().root(), 2f32);
+        assert_eq!(zones[0].sample().loop_points(), Some((20, 80)));
+    }
+
+    #[test]
+    fn missing_preset_is_an_error() {
+        assert!(SoundFont::parse_zones(Path::new("test.sf2"), &sound_font(), 0, 1).is_err());
+    }
+
+    #[test]
+    fn truncated_chunk_is_an_error() {
+        let file = sound_font();
+
+        assert!(SoundFont::parse_zones(Path::new("test.sf2"), &file[..file.len() - 30], 0, 0).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+use crate::note::Note;
+use crate::score::ScorePartSource;
+use crate::source::{oscillate, NoteRequest, SoundSource};
+use crate::source::pulse::pulse_wave;
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+const SQUARE_WIDTH: f32 = 0.5f32;
+
+fn wave(phase: f32, dt: f32) -> f32 {
+    pulse_wave(phase, dt, SQUARE_WIDTH)
+}
+
+pub struct Square;
+
+impl SoundSource for Square {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        Note::new(oscillate(&request, wave), DEFAULT_SAMPLE_RATE, None)
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::Square
+    }
+
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        Some(Note::new(oscillate(request, wave), request.sample_rate(), None))
+    }
+}
@@ -0,0 +1,140 @@
+use std::error::Error;
+use std::f32::consts::PI;
+
+use crate::envelope::Envelope;
+use crate::error::SourceError;
+use crate::filter::StateVariableFilter;
+use crate::lfo::Lfo;
+use crate::note::Note;
+use crate::score::{FilterMode, LfoTarget, OscillatorWaveform, ScorePartSource, ScoreSubtractive};
+use crate::source::{NoteRequest, SoundSource};
+use crate::source::pulse::pulse_wave;
+use crate::source::saw::saw_wave;
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+fn oscillator_wave(waveform: OscillatorWaveform, phase: f32, dt: f32) -> f32 {
+    match waveform {
+        OscillatorWaveform::Sin => (2.0 * PI * phase).sin(),
+        OscillatorWaveform::Triangle => (2.0 / PI) * ((2.0 * PI * phase).sin()).asin(),
+        OscillatorWaveform::Saw => saw_wave(phase, dt),
+        OscillatorWaveform::Square => pulse_wave(phase, dt, 0.5),
+        OscillatorWaveform::Pulse { width } => pulse_wave(phase, dt, width.clamp(0.01, 0.99)),
+    }
+}
+
+pub struct Subtractive {
+    score: ScoreSubtractive,
+    filter_envelope: Option<Envelope>,
+    lfo: Option<Lfo>,
+}
+
+impl Subtractive {
+    pub const MAX_OSCILLATORS: usize = 2;
+
+    pub fn new(score: ScoreSubtractive) -> Result<Self, Box<dyn Error>> {
+        let num_oscillators = score.oscillators().len();
+        if num_oscillators == 0 || num_oscillators > Self::MAX_OSCILLATORS {
+            return Err(Box::new(SourceError::new(format!(
+                "subtractive synth needs 1 to {} oscillators, got {}", Self::MAX_OSCILLATORS, num_oscillators
+            ))));
+        }
+
+        Ok(Self {
+            filter_envelope: score.filter().envelope().clone().map(|envelope| envelope.into()),
+            lfo: score.lfo().as_ref().map(|lfo| lfo.into()),
+            score,
+        })
+    }
+
+    fn render(&self, request: &NoteRequest) -> Vec<f32> {
+        let sample_rate = request.sample_rate();
+        let filter = self.score.filter();
+        let key_tracking = 2f32.powf(filter.key_tracking().unwrap_or(0f32) * request.semitone() / 12f32);
+        let envelope_depth = filter.envelope_depth().unwrap_or(0f32);
+        let lfo_pitch = self.score.lfo_pitch().unwrap_or(0f32);
+        let lfo_cutoff = self.score.lfo_cutoff().unwrap_or(0f32);
+
+        let mut phases = vec![0f32; self.score.oscillators().len()];
+        let mut svf = StateVariableFilter::new();
+        let mut data = Vec::with_capacity(request.length());
+        for n in 0..request.length() {
+            let t = n as f32 / sample_rate as f32;
+            let lfo = match self.lfo.as_ref() {
+                Some(lfo) => lfo.value(t),
+                None => 0f32,
+            };
+            let frequency = request.frequency() * 2f32.powf(lfo * lfo_pitch / 12f32);
+
+            let mut sample = 0f32;
+            for (phase, oscillator) in phases.iter_mut().zip(self.score.oscillators().iter()) {
+                let dt = frequency * 2f32.powf(oscillator.semitone().unwrap_or(0f32) / 12f32) / sample_rate as f32;
+                sample += oscillator.level().unwrap_or(1f32) * oscillator_wave(oscillator.waveform(), *phase, dt);
+                *phase = (*phase + dt).fract();
+            }
+
+            let envelope = match self.filter_envelope.as_ref() {
+                Some(envelope) => envelope.value(t, request.note_off()),
+                None => 0f32,
+            };
+            let octaves = envelope_depth * envelope + lfo_cutoff * lfo + request.lfo(LfoTarget::Cutoff, t);
+            let cutoff = filter.cutoff() * key_tracking * 2f32.powf(octaves);
+            let (low, band, high) = svf.process(sample, cutoff, filter.resonance(), sample_rate);
+            data.push(match filter.mode() {
+                FilterMode::LowPass => low,
+                FilterMode::BandPass => band,
+                FilterMode::HighPass => high,
+            });
+        }
+
+        data
+    }
+}
+
+impl SoundSource for Subtractive {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        Note::new(self.render(&request), DEFAULT_SAMPLE_RATE, None)
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::Subtractive(self.score.clone())
+    }
+
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        Some(Note::new(self.render(request), request.sample_rate(), None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::score::{ScoreEnvelope, ScoreFilter, ScoreOscillator};
+
+    // Mean slope over `seconds`, which grows with the high frequencies let through the filter.
+    fn brightness(data: &[f32], seconds: (f32, f32), sample_rate: u32) -> f32 {
+        let (start, end) = ((seconds.0 * sample_rate as f32) as usize, (seconds.1 * sample_rate as f32) as usize);
+        data[start..end].windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum::<f32>() / (end - start) as f32
+    }
+
+    #[test]
+    fn filter_envelope_releases_after_note_off() {
+        let filter = ScoreFilter::new(FilterMode::LowPass, 200f32, 0f32, None,
+            Some(ScoreEnvelope::new(0f32, 0f32, 1f32, 0.5)), Some(5f32));
+        let subtractive = Subtractive::new(ScoreSubtractive::new(
+            vec![ScoreOscillator::new(OscillatorWaveform::Saw, None, None)], filter, None, None, None,
+        )).unwrap();
+        let request = NoteRequest::new(0f32, 2f32, DEFAULT_SAMPLE_RATE).with_note_off(1f32);
+        let data = subtractive.render(&request);
+
+        let held = brightness(&data, (0.8, 0.9), DEFAULT_SAMPLE_RATE);
+        let releasing = brightness(&data, (1.2, 1.3), DEFAULT_SAMPLE_RATE);
+        let released = brightness(&data, (1.6, 1.7), DEFAULT_SAMPLE_RATE);
+        assert!(releasing < held);
+        assert!(released < releasing);
+        assert!(released < 0.5 * held);
+    }
+}
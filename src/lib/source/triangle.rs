@@ -2,29 +2,30 @@ use std::f32::consts::PI;
 
 use crate::note::Note;
 use crate::score::ScorePartSource;
-use crate::source::SoundSource;
+use crate::source::{oscillate, NoteRequest, SoundSource};
 
 
 const DEFAULT_SAMPLE_RATE: u32 = 48_000;
 const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
-const FREQUENCY_C4: f32 = 261.6256f32;
+
+fn wave(phase: f32, _: f32) -> f32 {
+    (2.0 / PI) * ((2.0 * PI * phase).sin()).asin()
+}
 
 pub struct Triangle;
 
 impl SoundSource for Triangle {
     fn get_base(&self) -> Note {
-        let length = (DEFAULT_DURATION_SECONDS * DEFAULT_SAMPLE_RATE as f32) as usize;
-        let data = (0..length)
-            .map(|i| {
-                let t = i as f32 / DEFAULT_SAMPLE_RATE as f32;
-                (2.0 / PI) * ((2.0 * PI * FREQUENCY_C4 * t).sin()).asin()
-            })
-        .collect();
-
-        Note::new(data, DEFAULT_SAMPLE_RATE, None)
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+
+        Note::new(oscillate(&request, wave), DEFAULT_SAMPLE_RATE, None)
     }
 
     fn to_score_part_source(&self) -> ScorePartSource {
         ScorePartSource::Triangle
     }
+
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        Some(Note::new(oscillate(request, wave), request.sample_rate(), None))
+    }
 }
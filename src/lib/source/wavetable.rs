@@ -0,0 +1,132 @@
+use std::error::Error;
+use std::f32::consts::PI;
+
+use crate::error::SourceError;
+use crate::note::Note;
+use crate::score::{LfoTarget, ScorePartSource, WavetableFrames};
+use crate::source::{oscillate, oscillate_with_time, NoteRequest, SoundSource};
+use crate::utils::{downmix_channels, read_wav};
+
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const DEFAULT_DURATION_SECONDS: f32 = 2.0f32;
+
+const BUILT_IN_FRAME_SIZE: usize = 2048;
+const BUILT_IN_HARMONICS: usize = 64;
+
+fn additive_frame(amplitude: impl Fn(usize) -> f32) -> Vec<f32> {
+    (0..BUILT_IN_FRAME_SIZE)
+        .map(|i| {
+            let x = i as f32 / BUILT_IN_FRAME_SIZE as f32;
+            (1..=BUILT_IN_HARMONICS)
+                .map(|k| amplitude(k) * (2.0 * PI * k as f32 * x).sin())
+                .sum()
+        })
+        .collect()
+}
+
+/// Sine, triangle, sawtooth and square, band-limited to `BUILT_IN_HARMONICS` partials.
+fn basic_frames() -> Vec<Vec<f32>> {
+    vec![
+        additive_frame(|k| if k == 1 { 1f32 } else { 0f32 }),
+        additive_frame(|k| if k % 2 == 1 {
+            let sign = if (k / 2) % 2 == 0 { 1f32 } else { -1f32 };
+            sign * 8f32 / (PI * PI * (k * k) as f32)
+        } else {
+            0f32
+        }),
+        additive_frame(|k| {
+            let sign = if k % 2 == 1 { 1f32 } else { -1f32 };
+            sign * 2f32 / (PI * k as f32)
+        }),
+        additive_frame(|k| if k % 2 == 1 { 4f32 / (PI * k as f32) } else { 0f32 }),
+    ]
+}
+
+pub struct Wavetable {
+    frames: WavetableFrames,
+    tables: Vec<Vec<f32>>,
+    position: f32,
+}
+
+impl Wavetable {
+    pub const DEFAULT_FRAME_SIZE: usize = 2048;
+
+    pub fn new(frames: WavetableFrames, position: f32) -> Result<Self, Box<dyn Error>> {
+        let tables = match &frames {
+            WavetableFrames::File { path, frame_size } => {
+                let frame_size = frame_size.unwrap_or(Self::DEFAULT_FRAME_SIZE);
+                if frame_size == 0 {
+                    return Err(Box::new(SourceError::new("wavetable frame size must be positive".to_string())));
+                }
+                let (channels, _) = read_wav(path)?;
+                let tables = downmix_channels(&channels)
+                    .chunks_exact(frame_size)
+                    .map(|frame| frame.to_vec())
+                    .collect::<Vec<_>>();
+                if tables.is_empty() {
+                    return Err(Box::new(SourceError::new(format!(
+                        "{} is shorter than one wavetable frame ({} samples)", path.display(), frame_size
+                    ))));
+                }
+                tables
+            },
+            WavetableFrames::Basic => basic_frames(),
+        };
+
+        Ok(Self {
+            frames,
+            tables,
+            position: position.clamp(0f32, 1f32),
+        })
+    }
+
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+
+    fn read_table(table: &[f32], phase: f32) -> f32 {
+        let pos = phase * table.len() as f32;
+        let i = (pos.floor() as usize).min(table.len() - 1);
+        let frac = pos - i as f32;
+        let s0 = table[i];
+        let s1 = table[(i + 1) % table.len()];
+
+        s0 + (s1 - s0) * frac
+    }
+
+    fn wave(&self, phase: f32, position: f32) -> f32 {
+        let index = position.clamp(0f32, 1f32) * (self.tables.len() - 1) as f32;
+        let i = (index.floor() as usize).min(self.tables.len() - 1);
+        let frac = index - i as f32;
+        let s0 = Self::read_table(&self.tables[i], phase);
+        if frac <= 0f32 || i + 1 >= self.tables.len() {
+            return s0;
+        }
+        let s1 = Self::read_table(&self.tables[i + 1], phase);
+
+        s0 + (s1 - s0) * frac
+    }
+}
+
+impl SoundSource for Wavetable {
+    fn get_base(&self) -> Note {
+        let request = NoteRequest::new(0f32, DEFAULT_DURATION_SECONDS, DEFAULT_SAMPLE_RATE);
+        let data = oscillate(&request, |phase, _| self.wave(phase, self.position));
+
+        Note::new(data, DEFAULT_SAMPLE_RATE, None)
+    }
+
+    fn to_score_part_source(&self) -> ScorePartSource {
+        ScorePartSource::Wavetable {
+            frames: self.frames.clone(),
+            position: self.position,
+        }
+    }
+
+    fn render_note(&self, request: &NoteRequest) -> Option<Note> {
+        let data = oscillate_with_time(request, |phase, _, t| self.wave(phase, self.position + request.lfo(LfoTarget::Position, t)));
+
+        Some(Note::new(data, request.sample_rate(), None))
+    }
+}
@@ -0,0 +1,223 @@
+use std::f32::consts::PI;
+
+use crate::score::StretchEngine;
+use crate::utils::{downmix_channels, fft, resample_linear};
+
+
+const WSOLA_FRAME_SECONDS: f32 = 0.04f32;
+const VOCODER_FRAME_SECONDS: f32 = 0.046f32;
+const VOCODER_OVERLAP: usize = 4;
+const CORRELATION_STEP: usize = 4;
+const MIN_OVERLAP_WEIGHT: f32 = 1e-3f32;
+
+/// Periodic Hann window, whose copies shifted by half its length sum to one.
+fn hann(length: usize) -> Vec<f32> {
+    (0..length).map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / length as f32).cos()).collect()
+}
+
+/// Divides overlap-added frames by the sum of their windows, which is short of one only at the start.
+fn normalize_overlap(output: Vec<f32>, weight: &[f32], length: usize) -> Vec<f32> {
+    output.into_iter().zip(weight.iter()).take(length)
+        .map(|(d, &w)| if w > MIN_OVERLAP_WEIGHT { d / w } else { 0f32 })
+        .collect()
+}
+
+fn wrap_phase(phase: f32) -> f32 {
+    phase - 2.0 * PI * (phase / (2.0 * PI)).round()
+}
+
+/// Overlap-adds Hann-windowed frames, taking each from around its nominal position where it
+/// best continues the previous one. The positions are searched on the mix of every channel
+/// so that the channels stay aligned.
+fn wsola(channels: &[Vec<f32>], ratio: f32, sample_rate: u32) -> Vec<Vec<f32>> {
+    let input_len = channels[0].len();
+    let output_len = (input_len as f32 * ratio) as usize;
+    let frame = ((WSOLA_FRAME_SECONDS * sample_rate as f32) as usize).max(4) & !1;
+    let hop = frame / 2;
+    let tolerance = hop / 2;
+    let window = hann(frame);
+    let last_position = input_len.saturating_sub(frame) as isize;
+    let mix = downmix_channels(channels);
+    let sample = |data: &[f32], i: isize| if i >= 0 && (i as usize) < data.len() { data[i as usize] } else { 0f32 };
+
+    let mut outputs = vec![vec![0f32; output_len + frame]; channels.len()];
+    let mut weight = vec![0f32; output_len + frame];
+    let mut previous: Option<isize> = None;
+    for k in 0..=output_len / hop {
+        // Frames stay inside the input, so that its end is held rather than faded out.
+        let nominal = ((k as f32 * hop as f32 / ratio) as isize).min(last_position);
+        let position = match previous {
+            // The part of the input that would have followed the previous frame.
+            Some(previous) => {
+                let natural = previous + hop as isize;
+                let correlation = |candidate: isize| (0..hop).step_by(CORRELATION_STEP)
+                    .map(|n| sample(&mix, candidate + n as isize) * sample(&mix, natural + n as isize))
+                    .sum::<f32>();
+                (-(tolerance as isize)..=tolerance as isize)
+                    .map(|offset| (nominal + offset).clamp(0, last_position))
+                    .map(|candidate| (candidate, correlation(candidate)))
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .map_or(nominal, |(candidate, _)| candidate)
+            },
+            None => nominal,
+        };
+        for (output, data) in outputs.iter_mut().zip(channels.iter()) {
+            for (n, w) in window.iter().enumerate() {
+                output[k * hop + n] += w * sample(data, position + n as isize);
+            }
+        }
+        for (n, w) in window.iter().enumerate() {
+            weight[k * hop + n] += w;
+        }
+        previous = Some(position);
+    }
+
+    outputs.into_iter().map(|output| normalize_overlap(output, &weight, output_len)).collect()
+}
+
+/// Keeps the magnitude of each frame and advances its phases at their measured frequencies
+/// by the synthesis hop, so that partials keep their pitch at any stretch.
+fn phase_vocoder(data: &[f32], ratio: f32, sample_rate: u32) -> Vec<f32> {
+    let output_len = (data.len() as f32 * ratio) as usize;
+    let frame = ((VOCODER_FRAME_SECONDS * sample_rate as f32) as usize).next_power_of_two().max(VOCODER_OVERLAP);
+    let hop = frame / VOCODER_OVERLAP;
+    let window = hann(frame);
+    let bins = frame / 2 + 1;
+    let omega: Vec<f32> = (0..bins).map(|k| 2.0 * PI * k as f32 / frame as f32).collect();
+    let mut frequency = omega.clone();
+
+    let mut output = vec![0f32; output_len + frame];
+    let mut weight = vec![0f32; output_len + frame];
+    let mut previous_phase = vec![0f32; bins];
+    let mut phase = vec![0f32; bins];
+    let mut previous_position: Option<usize> = None;
+    let mut re = vec![0f32; frame];
+    let mut im = vec![0f32; frame];
+    for k in 0..=output_len / hop {
+        let position = ((k as f32 * hop as f32 / ratio).round() as usize).min(data.len().saturating_sub(frame));
+        for n in 0..frame {
+            re[n] = data.get(position + n).copied().unwrap_or(0f32) * window[n];
+            im[n] = 0f32;
+        }
+        fft(&mut re, &mut im, false);
+
+        for bin in 0..bins {
+            let magnitude = re[bin].hypot(im[bin]);
+            let analysis_phase = im[bin].atan2(re[bin]);
+            match previous_position {
+                Some(previous) if position > previous => {
+                    let elapsed = (position - previous) as f32;
+                    let deviation = wrap_phase(analysis_phase - previous_phase[bin] - omega[bin] * elapsed);
+                    frequency[bin] = omega[bin] + deviation / elapsed;
+                    phase[bin] += frequency[bin] * hop as f32;
+                },
+                // A held frame keeps the frequencies last measured.
+                Some(_) => phase[bin] += frequency[bin] * hop as f32,
+                None => phase[bin] = analysis_phase,
+            }
+            previous_phase[bin] = analysis_phase;
+
+            let (sin, cos) = phase[bin].sin_cos();
+            re[bin] = magnitude * cos;
+            im[bin] = magnitude * sin;
+            // The spectrum of a real signal is conjugate symmetric.
+            if bin > 0 && bin < frame - bin {
+                re[frame - bin] = re[bin];
+                im[frame - bin] = -im[bin];
+            }
+        }
+        previous_position = Some(position);
+        fft(&mut re, &mut im, true);
+
+        for n in 0..frame {
+            output[k * hop + n] += re[n] * window[n];
+            weight[k * hop + n] += window[n] * window[n];
+        }
+    }
+
+    normalize_overlap(output, &weight, output_len)
+}
+
+/// Makes the channels `ratio` times as long. `Resample` also changes their pitch,
+/// the other engines keep it.
+pub fn time_stretch(channels: &[Vec<f32>], ratio: f32, engine: StretchEngine, sample_rate: u32) -> Vec<Vec<f32>> {
+    if channels.is_empty() || channels[0].is_empty() || ratio <= 0f32 {
+        return channels.to_vec();
+    }
+
+    match engine {
+        StretchEngine::Resample => channels.iter().map(|data| resample_linear(data.clone(), 1f32 / ratio)).collect(),
+        StretchEngine::Wsola => wsola(channels, ratio, sample_rate),
+        StretchEngine::PhaseVocoder => channels.iter().map(|data| phase_vocoder(data, ratio, sample_rate)).collect(),
+    }
+}
+
+/// Shifts the pitch of the channels by `factor`. `Resample` also shortens them by it,
+/// the other engines keep their length.
+pub fn pitch_shift(channels: &[Vec<f32>], factor: f32, engine: StretchEngine, sample_rate: u32) -> Vec<Vec<f32>> {
+    if factor == 1f32 {
+        return channels.to_vec();
+    }
+
+    let stretched = match engine {
+        StretchEngine::Resample => channels.to_vec(),
+        _ => time_stretch(channels, factor, engine, sample_rate),
+    };
+
+    stretched.into_iter().map(|data| resample_linear(data, factor)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 44_100;
+
+    fn sine(frequency: f32, length: usize) -> Vec<f32> {
+        (0..length).map(|n| (2f32 * PI * frequency * n as f32 / SAMPLE_RATE as f32).sin()).collect()
+    }
+
+    /// Frequency from the upward zero crossings of the middle half of `data`.
+    fn frequency(data: &[f32]) -> f32 {
+        let middle = &data[data.len() / 4..data.len() * 3 / 4];
+        let crossings: Vec<f32> = middle.windows(2).enumerate()
+            .filter(|(_, pair)| pair[0] <= 0f32 && pair[1] > 0f32)
+            .map(|(n, pair)| n as f32 + pair[0] / (pair[0] - pair[1]))
+            .collect();
+        let periods = (crossings.len() - 1) as f32;
+
+        SAMPLE_RATE as f32 * periods / (crossings[crossings.len() - 1] - crossings[0])
+    }
+
+    #[test]
+    fn stretch_scales_length() {
+        let channels = vec![sine(440f32, SAMPLE_RATE as usize)];
+        for engine in [StretchEngine::Wsola, StretchEngine::PhaseVocoder] {
+            for ratio in [0.5f32, 1f32, 2f32] {
+                let stretched = time_stretch(&channels, ratio, engine, SAMPLE_RATE);
+                assert_eq!(stretched[0].len(), (SAMPLE_RATE as f32 * ratio) as usize);
+            }
+        }
+    }
+
+    #[test]
+    fn stretch_keeps_pitch() {
+        let channels = vec![sine(440f32, SAMPLE_RATE as usize)];
+        for engine in [StretchEngine::Wsola, StretchEngine::PhaseVocoder] {
+            let stretched = time_stretch(&channels, 2f32, engine, SAMPLE_RATE);
+            assert!((frequency(&stretched[0]) - 440f32).abs() < 2f32);
+        }
+    }
+
+    #[test]
+    fn pitch_shift_keeps_length_and_moves_frequency() {
+        let channels = vec![sine(440f32, SAMPLE_RATE as usize)];
+        let factor = 2f32.powf(3f32 / 12f32);
+        for engine in [StretchEngine::Wsola, StretchEngine::PhaseVocoder] {
+            let shifted = pitch_shift(&channels, factor, engine, SAMPLE_RATE);
+            assert!(shifted[0].len().abs_diff(channels[0].len()) <= 1);
+            let cents = 1200f32 * (frequency(&shifted[0]) / (440f32 * factor)).log2();
+            assert!(cents.abs() < 5f32);
+        }
+    }
+}
@@ -6,12 +6,13 @@ use hound::{WavWriter, WavSpec, SampleFormat};
 use crate::envelope::Envelope;
 use crate::error::IndexError;
 use crate::note::Note;
-use crate::utils::{normalize_data, resample_data};
+use crate::score::Downmix;
+use crate::utils::{downmix_channels, normalize_channels, normalize_data, resample_data};
 
 
 #[derive(Clone)]
 pub struct InstrumentTrack {
-    data: Vec<f32>,
+    channels: Vec<Vec<f32>>,
     sample_rate: u32,
     volume: f32,
     envelope: Option<Envelope>,
@@ -20,47 +21,77 @@ pub struct InstrumentTrack {
 impl InstrumentTrack {
     pub fn new(sample_rate: u32, volume: f32, envelope: Option<Envelope>) -> Self {
         Self {
-            data: vec![],
+            channels: vec![vec![]],
             sample_rate,
             volume,
             envelope,
         }
     }
 
-    pub fn data(&self) -> Vec<f32> {
-        normalize_data(self.data.clone(), self.volume)
+    /// Every channel, normalized together so that the loudest sample reaches the volume.
+    pub fn data(&self) -> Vec<Vec<f32>> {
+        normalize_channels(self.channels.clone(), self.volume)
     }
 
     pub fn sample_rate(&self) -> u32 {
         self.sample_rate
     }
 
+    pub fn envelope(&self) -> &Option<Envelope> {
+        &self.envelope
+    }
+
     pub fn length(&self) -> usize {
-        self.data.len()
+        self.channels.iter().map(|data| data.len()).max().unwrap_or(0)
     }
 
-    pub fn add_note(&mut self, start: Option<f32>, note: Note) {
-        let note = match note.to_owned().envelope().as_ref() {
-            Some(envelope) => envelope.apply(note),
-            None => match self.envelope.as_ref() {
-                Some(envelope) => envelope.apply(note),
-                None => note,
-            },
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Mixes the channels into one by the given policy.
+    pub fn downmix(self, downmix: Downmix) -> Self {
+        let data = match downmix {
+            Downmix::Mix => downmix_channels(&self.channels),
+            Downmix::Left => self.channels[0].clone(),
+            Downmix::Right => self.channels[1.min(self.channels.len() - 1)].clone(),
+        };
+
+        Self {
+            channels: vec![data],
+            ..self
+        }
+    }
+
+    /// Adds the note released `note_off` seconds after its start, shaped by its own envelope or the track's.
+    /// Overlapping notes are summed.
+    pub fn add_note(&mut self, start: Option<f32>, note: Note, note_off: f32) {
+        let note = match note.envelope().clone().or(self.envelope.clone()) {
+            Some(envelope) => envelope.apply(note, note_off),
+            None => note,
         };
-        let data = resample_data(note.data().clone(), note.sample_rate(), self.sample_rate);
         let start = match start {
             Some(start) => {
                 (start * self.sample_rate as f32) as usize
             },
             None => {
-                self.data.len()
+                self.length()
             }
         };
-        for (i, &d) in data.iter().enumerate() {
-            while self.data.len() <= start + i {
-                self.data.push(0f32);
+        // A mono track becomes multi-channel with its first multi-channel note.
+        if note.channels().len() > self.channels.len() && self.channels.len() == 1 {
+            self.channels = vec![self.channels[0].clone(); note.channels().len()];
+        }
+        for (channel, track_data) in self.channels.iter_mut().enumerate() {
+            // Mono notes are spread over every channel.
+            let note_data = &note.channels()[channel.min(note.channels().len() - 1)];
+            let data = resample_data(note_data.clone(), note.sample_rate(), self.sample_rate);
+            for (i, &d) in data.iter().enumerate() {
+                while track_data.len() <= start + i {
+                    track_data.push(0f32);
+                }
+                track_data[start + i] += d;
             }
-            self.data[start + i] += d;
         }
     }
 }
@@ -96,12 +127,23 @@ impl Mixdown {
     }
 
     pub fn channel_data(&self, channel: u16) -> Result<Vec<f32>, Box<dyn Error>> {
+        let (data, volume) = self.channel_sum(channel)?;
+
+        Ok(normalize_data(data, volume))
+    }
+
+    /// Sum of every track playing on the channel, with the loudest volume among them.
+    fn channel_sum(&self, channel: u16) -> Result<(Vec<f32>, f32), Box<dyn Error>> {
         match self.tracks.get(channel as usize) {
-            Some(channel_track) => {
+            Some(_) => {
                 let mut track_data_list = vec![];
                 let mut volume = 0f32;
-                for track in channel_track.iter() {
-                    track_data_list.push(resample_data(track.data.clone(), track.sample_rate, self.sample_rate));
+                // Multi-channel tracks added to earlier channels also cover this one.
+                let tracks = self.tracks[..=channel as usize].iter().enumerate()
+                    .flat_map(|(first, tracks)| tracks.iter().map(move |track| (channel as usize - first, track)))
+                    .filter(|(offset, track)| *offset < track.num_channels());
+                for (offset, track) in tracks {
+                    track_data_list.push(resample_data(track.channels[offset].clone(), track.sample_rate, self.sample_rate));
                     if volume < track.volume {
                         volume = track.volume;
                     }
@@ -115,17 +157,37 @@ impl Mixdown {
                     }
                 }
 
-                Ok(normalize_data(data, volume))
+                Ok((data, volume))
             },
             None => Err(Box::new(IndexError::new(channel as usize, self.channel as usize))),
         }
     }
 
     pub fn data(&self) -> Result<Vec<f32>, Box::<dyn Error>> {
+        // Channels shared by a multi-channel track are normalized together to keep its balance.
+        let mut group_ends: Vec<usize> = (1..=self.channel as usize).collect();
+        for (first, tracks) in self.tracks.iter().enumerate() {
+            for track in tracks {
+                let end = first + track.num_channels();
+                for group_end in group_ends[first..end].iter_mut() {
+                    *group_end = (*group_end).max(end);
+                }
+            }
+        }
         let mut channel_data_list = vec![];
-        for channel in 0..self.channel {
-            let channel_data = self.channel_data(channel)?;
-            channel_data_list.push(channel_data);
+        let mut channel = 0usize;
+        while channel < self.channel as usize {
+            let mut end = group_ends[channel];
+            let mut group = vec![];
+            let mut volume = 0f32;
+            while channel < end {
+                end = end.max(group_ends[channel]);
+                let (data, channel_volume) = self.channel_sum(channel as u16)?;
+                group.push(data);
+                volume = volume.max(channel_volume);
+                channel += 1;
+            }
+            channel_data_list.extend(normalize_channels(group, volume));
         }
         let length = channel_data_list.iter().map(|channel_data| channel_data.len()).max().or(Some(0usize)).unwrap();
         let mut data = vec![0f32; length * self.channel as usize];
@@ -138,7 +200,12 @@ impl Mixdown {
         Ok(data)
     }
 
+    /// Adds a track whose first channel goes to `channel` and the others to the following channels.
     pub fn add_track(&mut self, channel: u16, track: InstrumentTrack) -> Result<(), Box<dyn Error>> {
+        let last = channel as usize + track.num_channels() - 1;
+        if last >= self.channel as usize {
+            return Err(Box::new(IndexError::new(last, self.channel as usize)));
+        }
         match self.tracks.get_mut(channel as usize) {
             Some(channel_track) => {
                 channel_track.push(track);
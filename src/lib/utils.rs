@@ -1,3 +1,13 @@
+use std::error::Error;
+use std::f32::consts::PI;
+use std::path::Path;
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+
+/// Channels of samples in [-1, 1] and their sample rate.
+pub type WavData = (Vec<Vec<f32>>, u32);
+
+
 pub fn resample_linear(data: Vec<f32>, factor: f32) -> Vec<f32> {
     if data.is_empty() {
         return data;
@@ -38,3 +48,116 @@ pub fn normalize_data(data: Vec<f32>, peak: f32) -> Vec<f32> {
 
     out
 }
+
+/// Scales every channel by the same gain so that the loudest sample reaches `peak`.
+pub fn normalize_channels(channels: Vec<Vec<f32>>, peak: f32) -> Vec<Vec<f32>> {
+    let m = channels.iter().flatten().fold(0f32, |a, &x| a.max(x.abs()));
+    if m > 0.0 {
+        let g = peak / m;
+        channels.into_iter().map(|data| data.into_iter().map(|s| s * g).collect()).collect()
+    } else {
+        channels
+    }
+}
+
+/// Averages the channels into one.
+pub fn downmix_channels(channels: &[Vec<f32>]) -> Vec<f32> {
+    let len = channels.iter().map(|data| data.len()).max().unwrap_or(0);
+    let mut out = vec![0f32; len];
+    for data in channels.iter() {
+        for (o, &d) in out.iter_mut().zip(data.iter()) {
+            *o += d / channels.len() as f32;
+        }
+    }
+
+    out
+}
+
+/// Decodes every channel of a WAV file into samples in [-1, 1].
+pub fn read_wav(src_file: &Path) -> Result<WavData, Box<dyn Error>> {
+    let mut reader = WavReader::open(src_file)?;
+    let spec = reader.spec();
+
+    let samples: Vec<f32> = if spec.sample_format == SampleFormat::Float {
+        reader
+            .samples::<f32>()
+            .map(|s| s.unwrap_or(0f32))
+            .collect()
+    } else {
+        let scale = (1i64 << (spec.bits_per_sample.max(1) - 1)) as f32;
+        reader
+            .samples::<i32>()
+            .map(|s| s.unwrap_or(0) as f32 / scale)
+            .collect()
+    };
+
+    let num_channels = spec.channels.max(1) as usize;
+    let channels = (0..num_channels)
+        .map(|channel| samples.iter().skip(channel).step_by(num_channels).copied().collect())
+        .collect();
+
+    Ok((channels, spec.sample_rate))
+}
+
+/// Writes interleaved 32-bit float samples of every channel.
+pub fn write_wav(path: &Path, channels: &[Vec<f32>], sample_rate: u32) -> Result<(), Box<dyn Error>> {
+    let spec = WavSpec {
+        channels: channels.len() as u16,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let mut writer = WavWriter::create(path, spec)?;
+    for i in 0..channels.iter().map(|data| data.len()).max().unwrap_or(0) {
+        for data in channels.iter() {
+            writer.write_sample(data.get(i).copied().unwrap_or(0f32))?;
+        }
+    }
+    writer.finalize()?;
+
+    Ok(())
+}
+
+/// In-place radix-2 FFT. The length must be a power of two.
+pub fn fft(re: &mut [f32], im: &mut [f32], inverse: bool) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1f32 } else { -1f32 };
+    let mut length = 2;
+    while length <= n {
+        let angle = sign * 2.0 * PI / length as f32;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (w_im, w_re) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + length / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        length <<= 1;
+    }
+
+    if inverse {
+        for (r, i) in re.iter_mut().zip(im.iter_mut()) {
+            *r /= n as f32;
+            *i /= n as f32;
+        }
+    }
+}